@@ -127,6 +127,7 @@ const (
 	FURY_BUFFER                 = 266
 	FURY_ARROW_RECORD_BATCH     = 267
 	FURY_ARROW_TABLE            = 268
+	FURY_ENUM                   = 269
 )
 
 const (
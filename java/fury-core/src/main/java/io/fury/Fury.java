@@ -85,6 +85,7 @@ public final class Fury {
   public static final byte REF_VALUE_FLAG = 0;
   public static final byte NOT_SUPPORT_CROSS_LANGUAGE = 0;
   public static final short FURY_TYPE_TAG_ID = Type.FURY_TYPE_TAG.getId();
+  public static final short FURY_ENUM_ID = Type.FURY_ENUM.getId();
   private static final byte isNilFlag = 1;
   private static final byte isLittleEndianFlag = 1 << 1;
   private static final byte isCrossLanguageFlag = 1 << 2;
@@ -483,10 +484,18 @@ public final class Fury {
     depth++;
     @SuppressWarnings("unchecked")
     Class<T> cls = (Class<T>) obj.getClass();
+    short typeId;
     if (serializer == null) {
       serializer = classResolver.getSerializer(cls);
+      typeId = serializer.getXtypeId();
+      // The enum type id doesn't tell which enum it is, the reader only knows it from the
+      // declared type.
+      if (typeId == FURY_ENUM_ID) {
+        typeId = NOT_SUPPORT_CROSS_LANGUAGE;
+      }
+    } else {
+      typeId = serializer.getXtypeId();
     }
-    short typeId = serializer.getXtypeId();
     buffer.writeShort(typeId);
     if (typeId != NOT_SUPPORT_CROSS_LANGUAGE) {
       if (typeId == FURY_TYPE_TAG_ID) {
@@ -960,7 +969,10 @@ public final class Fury {
           cls = classResolver.xreadClass(buffer);
         }
       } else {
-        if (typeId != FURY_TYPE_TAG_ID) {
+        if (typeId == FURY_ENUM_ID) {
+          Preconditions.checkNotNull(serializer, "Enum is only read with the declared type");
+          cls = serializer.getType();
+        } else if (typeId != FURY_TYPE_TAG_ID) {
           cls = classResolver.getClassByTypeId(typeId);
         }
       }
@@ -331,6 +331,21 @@ public class Serializers {
     public Enum read(MemoryBuffer buffer) {
       return enumConstants[buffer.readPositiveVarInt()];
     }
+
+    @Override
+    public short getXtypeId() {
+      return Type.FURY_ENUM.getId();
+    }
+
+    @Override
+    public void xwrite(MemoryBuffer buffer, Enum value) {
+      buffer.writePositiveVarInt(value.ordinal());
+    }
+
+    @Override
+    public Enum xread(MemoryBuffer buffer) {
+      return enumConstants[buffer.readPositiveVarInt()];
+    }
   }
 
   public static final class BigDecimalSerializer extends Serializer<BigDecimal> {
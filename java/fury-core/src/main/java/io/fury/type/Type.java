@@ -161,7 +161,8 @@ public enum Type {
   FURY_SERIALIZED_OBJECT(265),
   FURY_BUFFER(266),
   FURY_ARROW_RECORD_BATCH(267),
-  FURY_ARROW_TABLE(268);
+  FURY_ARROW_TABLE(268),
+  FURY_ENUM(269);
 
   private short id;
 
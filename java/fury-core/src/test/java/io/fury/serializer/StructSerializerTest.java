@@ -0,0 +1,90 @@
+/*
+ * Copyright 2023 The Fury Authors
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+package io.fury.serializer;
+
+import static org.testng.Assert.assertEquals;
+
+import io.fury.Fury;
+import io.fury.FuryTestBase;
+import io.fury.config.Language;
+import java.time.Instant;
+import java.time.LocalDate;
+import java.util.Arrays;
+import java.util.List;
+import java.util.Map;
+import java.util.Set;
+import org.testng.annotations.Test;
+
+public class StructSerializerTest extends FuryTestBase {
+
+  public enum Color {
+    Red,
+    Green,
+    Blue
+  }
+
+  public static class Pick {
+    public Color color;
+  }
+
+  public static class Bar {
+    public String category;
+  }
+
+  public static class Foo {
+    public byte type;
+    public Map<String, List<Double>> scores;
+    public Set<Integer> ids;
+    public LocalDate date;
+    public Instant created;
+    public byte[] bin;
+    public Bar bar;
+    public List<Bar> animals;
+  }
+
+  @Test
+  public void testStructHash() {
+    Fury fury =
+        Fury.builder()
+            .withLanguage(Language.XLANG)
+            .withRefTracking(true)
+            .requireClassRegistration(false)
+            .build();
+    fury.register(Bar.class, "example.bar");
+    fury.register(Foo.class, "example.foo");
+    // rust and python check the same hashes in `struct_hash` and `test_struct_hash`,
+    // the set field is left out.
+    StructSerializer<?> serializer =
+        (StructSerializer<?>) fury.getClassResolver().getSerializer(Bar.class);
+    assertEquals(serializer.computeStructHash(), 540);
+    serializer = (StructSerializer<?>) fury.getClassResolver().getSerializer(Foo.class);
+    assertEquals(serializer.computeStructHash(), 1300969400);
+  }
+
+  @Test
+  public void testEnumField() {
+    Fury fury = Fury.builder().withLanguage(Language.XLANG).requireClassRegistration(false).build();
+    fury.register(Pick.class, "example.pick");
+    Pick pick = new Pick();
+    pick.color = Color.Blue;
+    byte[] bytes = fury.serialize(pick);
+    // rust checks the same bytes in `java`: the enum type id and the ordinal.
+    assertEquals(
+        Arrays.copyOfRange(bytes, bytes.length - 4, bytes.length), new byte[] {-1, 13, 1, 2});
+    assertEquals(((Pick) fury.deserialize(bytes)).color, Color.Blue);
+  }
+}
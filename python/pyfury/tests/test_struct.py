@@ -12,6 +12,7 @@
 # See the License for the specific language governing permissions and
 # limitations under the License.
 
+import datetime
 from dataclasses import dataclass
 from typing import Dict, Any, List
 
@@ -20,6 +21,7 @@ import typing
 
 import pyfury
 from pyfury import Fury, Language
+from pyfury._struct import _get_hash
 
 
 def ser_de(fury, obj):
@@ -78,6 +80,34 @@ def test_struct():
         assert ser_de(fury, ComplexObject(f6=2**64)) == ComplexObject(f6=2**64)
 
 
+@dataclass
+class Bar:
+    category: str = None
+
+
+@dataclass
+class Foo:
+    type: pyfury.Int8Type = 0
+    scores: Dict[str, List[pyfury.Float64Type]] = None
+    ids: set = None
+    date: datetime.date = None
+    created: datetime.datetime = None
+    bin: bytes = None
+    bar: Bar = None
+    animals: List[Bar] = None
+
+
+def test_struct_hash():
+    # java and rust check the same hashes in `testStructHash` and `struct_hash`,
+    # the set field is left out.
+    fury = Fury(language=Language.XLANG, ref_tracking=True)
+    fury.register_class(Bar, type_tag="example.bar")
+    fury.register_class(Foo, type_tag="example.foo")
+    for cls, hash_ in ((Bar, 540), (Foo, 1300969400)):
+        type_hints = typing.get_type_hints(cls)
+        assert _get_hash(fury, sorted(type_hints.keys()), type_hints) == hash_
+
+
 @dataclass
 class SuperClass1:
     f1: Any = None
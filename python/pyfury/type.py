@@ -218,6 +218,7 @@ class FuryType(enum.Enum):
     FURY_BUFFER = 266
     FURY_ARROW_RECORD_BATCH = 267
     FURY_ARROW_TABLE = 268
+    FURY_ENUM = 269
 
 
 Int8Type = TypeVar("Int8Type", bound=int)
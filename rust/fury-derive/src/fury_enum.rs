@@ -0,0 +1,242 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{DataEnum, Fields};
+
+/// The pattern which binds the fields of a variant, tuple fields are bound as `f0`, `f1`...
+fn bindings(fields: &Fields) -> Vec<proc_macro2::Ident> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(ident) => ident.clone(),
+            None => format_ident!("f{}", i),
+        })
+        .collect()
+}
+
+fn pattern(fields: &Fields, bindings: &[proc_macro2::Ident]) -> proc_macro2::TokenStream {
+    match fields {
+        Fields::Named(_) => quote! { { #(#bindings),* } },
+        Fields::Unnamed(_) => quote! { ( #(#bindings),* ) },
+        Fields::Unit => quote! {},
+    }
+}
+
+/// Derive the serialization of an enum.
+///
+/// An enum whose variants have no fields is written with the enum type id like `EnumSerializer` in Java,
+/// followed by the ordinal of the variant, or by its name with `#[by_name]`.
+/// Other enums are written like a struct with their tag and hash, the variant follows the same way
+/// and its fields are written after it in the order they are declared.
+pub fn derive_enum(
+    ast: &syn::DeriveInput,
+    data: &DataEnum,
+    tag: String,
+    by_name: bool,
+) -> TokenStream {
+    let name = &ast.ident;
+    let variants: Vec<_> = data.variants.iter().collect();
+    let name_hash_static: proc_macro2::Ident =
+        syn::Ident::new(&format!("HASH_{}", name).to_uppercase(), name.span());
+    // only the variant is written, without the tag and the hash
+    let c_like = variants
+        .iter()
+        .all(|variant| matches!(variant.fields, Fields::Unit));
+
+    // variants written by name may be reordered, so their hash doesn't depend on the order
+    let mut hashed = variants.clone();
+    if by_name {
+        hashed.sort_by_key(|variant| variant.ident.to_string());
+    }
+    let variant_props = hashed.iter().map(|variant| {
+        let variant_name = variant.ident.to_string();
+        let props = variant.fields.iter().enumerate().map(|(i, field)| {
+            let ty = &field.ty;
+            let field_name = match &field.ident {
+                Some(ident) => ident.to_string(),
+                None => i.to_string(),
+            };
+            quote! {
+                (#field_name, <#ty as fury::__derive::FuryMeta>::ty(), <#ty as fury::__derive::FuryMeta>::tag())
+            }
+        });
+        quote! {
+            (#variant_name, fury::__derive::compute_struct_hash(vec![#(#props),*]))
+        }
+    });
+
+    let write_arms = variants.iter().enumerate().map(|(i, variant)| {
+        let ident = &variant.ident;
+        let bindings = bindings(&variant.fields);
+        let pattern = pattern(&variant.fields, &bindings);
+        let key = if by_name {
+            let variant_name = ident.to_string();
+            quote! { serializer.write_field_name(#variant_name); }
+        } else {
+            let ordinal = i as u32;
+            quote! { serializer.writer.var_uint32(#ordinal); }
+        };
+        quote! {
+            Self::#ident #pattern => {
+                #key
+                #(fury::__derive::Serialize::serialize_field(#bindings, serializer);)*
+            }
+        }
+    });
+
+    let size_arms = variants.iter().enumerate().map(|(i, variant)| {
+        let ident = &variant.ident;
+        let bindings = bindings(&variant.fields);
+        let pattern = pattern(&variant.fields, &bindings);
+        let key = if by_name {
+            let variant_name = ident.to_string();
+            quote! { serializer.field_name_size(#variant_name) }
+        } else {
+            let ordinal = i as u32;
+            quote! { fury::__derive::Writer::var_uint32_size(#ordinal) }
+        };
+        quote! {
+            Self::#ident #pattern => #key #(+ fury::__derive::Serialize::field_size(#bindings, serializer))*
+        }
+    });
+
+    let read_arms = variants.iter().enumerate().map(|(i, variant)| {
+        let ident = &variant.ident;
+        let reads = variant.fields.iter().map(|field| {
+            let ty = &field.ty;
+            quote! { <#ty as fury::__derive::Deserialize>::deserialize_field(deserializer)? }
+        });
+        let value = match &variant.fields {
+            Fields::Named(_) => {
+                let idents = variant.fields.iter().map(|field| &field.ident);
+                quote! { Self::#ident { #(#idents: #reads),* } }
+            }
+            Fields::Unnamed(_) => quote! { Self::#ident ( #(#reads),* ) },
+            Fields::Unit => quote! { Self::#ident },
+        };
+        let ordinal = i as u32;
+        quote! {
+            #ordinal => Ok(#value)
+        }
+    });
+
+    let read_key = if by_name {
+        let name_arms = variants.iter().enumerate().map(|(i, variant)| {
+            let lit = syn::LitByteStr::new(variant.ident.to_string().as_bytes(), name.span());
+            let ordinal = i as u32;
+            quote! { #lit => #ordinal }
+        });
+        quote! {{
+            let len = deserializer.reader.var_uint32()?;
+            let bytes = deserializer.reader.bytes(len as usize)?;
+            match bytes {
+                #(#name_arms,)*
+                _ => {
+                    let name = String::from_utf8_lossy(bytes).to_string();
+                    return Err(fury::__derive::Error::UnknownVariant(name));
+                }
+            }
+        }}
+    } else {
+        quote! { deserializer.reader.var_uint32()? }
+    };
+
+    let tag_bytelen = tag.len();
+    let (ty, write_head, head_size, read_head) = if c_like {
+        (
+            quote! { fury::__derive::FieldType::FuryEnum },
+            quote! {},
+            quote! {},
+            quote! {},
+        )
+    } else {
+        (
+            quote! { fury::__derive::FieldType::FuryTypeTag },
+            quote! {
+                serializer.write_tag(<#name as fury::__derive::FuryMeta>::tag());
+                serializer.writer.u32(<#name as fury::__derive::FuryMeta>::hash());
+            },
+            // tag and four byte hash
+            quote! { serializer.tag_size(<#name as fury::__derive::FuryMeta>::tag()) + 4 + },
+            quote! {
+                deserializer.read_tag()?;
+                let hash = deserializer.reader.u32()?;
+                let expected = <#name as fury::__derive::FuryMeta>::hash();
+                if hash != expected {
+                    return Err(fury::__derive::Error::StructHash{ expected, actial: hash });
+                }
+            },
+        )
+    };
+    let reserved_space = if c_like {
+        quote! { 4 }
+    } else {
+        // the tag, the hash and the variant, the fields reserve for themselves
+        quote! { #tag_bytelen + 4 + 4 }
+    };
+
+    let gen = quote! {
+        lazy_static::lazy_static! {
+            static ref #name_hash_static: u32 = fury::__derive::compute_enum_hash(vec![#(#variant_props),*]);
+        }
+
+        impl fury::__derive::FuryMeta for #name {
+            fn tag() -> &'static str {
+                #tag
+            }
+
+            fn hash() -> u32 {
+                *(#name_hash_static)
+            }
+
+            fn ty() -> fury::__derive::FieldType {
+                #ty
+            }
+        }
+
+        impl fury::__derive::Serialize for #name {
+            fn write(&self, serializer: &mut fury::__derive::SerializerState) {
+                #write_head
+                match self {
+                    #(#write_arms)*
+                }
+            }
+
+            fn size(&self, serializer: &mut fury::__derive::SerializerState) -> usize {
+                #head_size match self {
+                    #(#size_arms,)*
+                }
+            }
+
+            fn reserved_space() -> usize {
+                #reserved_space
+            }
+        }
+
+        impl fury::__derive::Deserialize for #name {
+            fn read(deserializer: &mut fury::__derive::DeserializerState) -> Result<Self, fury::__derive::Error> {
+                #read_head
+                let ordinal: u32 = #read_key;
+                match ordinal {
+                    #(#read_arms,)*
+                    _ => Err(fury::__derive::Error::UnknownVariant(ordinal.to_string())),
+                }
+            }
+        }
+    };
+    gen.into()
+}
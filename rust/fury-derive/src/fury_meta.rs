@@ -13,12 +13,36 @@
 // limitations under the License.
 
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{Field, Fields};
+use quote::{format_ident, quote};
+use syn::{Field, Fields, Member};
 
-pub fn sorted_fields(fields: &Fields) -> Vec<&Field> {
-    let mut fields = fields.iter().collect::<Vec<&Field>>();
-    fields.sort_by(|a, b| a.ident.cmp(&b.ident));
+/// The name of a field as other languages see it, raw identifiers are taken without `r#`
+/// and the fields of a tuple struct are named by their index.
+pub fn field_name(member: &Member) -> String {
+    match member {
+        Member::Named(ident) => ident.to_string().trim_start_matches("r#").to_string(),
+        Member::Unnamed(index) => index.index.to_string(),
+    }
+}
+
+/// The fields in the order they are written, with the member they are accessed by.
+///
+/// Named fields are sorted by name like `String.compareTo` in Java does, the fields of a tuple struct keep their order.
+pub fn sorted_fields(fields: &Fields) -> Vec<(Member, &Field)> {
+    let named = matches!(fields, Fields::Named(_));
+    let mut fields = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(ident) => (Member::Named(ident.clone()), field),
+            None => (Member::Unnamed(i.into()), field),
+        })
+        .collect::<Vec<_>>();
+    if named {
+        fields.sort_by_cached_key(|(member, _)| {
+            field_name(member).encode_utf16().collect::<Vec<u16>>()
+        });
+    }
     fields
 }
 
@@ -30,9 +54,9 @@ pub fn derive_fury_meta(ast: &syn::DeriveInput, tag: String) -> TokenStream {
             panic!("only struct be supported")
         }
     };
-    let props = fields.iter().map(|field| {
+    let props = fields.iter().map(|(member, field)| {
         let ty = &field.ty;
-        let name = format!("{}", field.ident.as_ref().expect("should be field name"));
+        let name = field_name(member);
         quote! {
             (#name, <#ty as fury::__derive::FuryMeta>::ty(), <#ty as fury::__derive::FuryMeta>::tag())
         }
@@ -63,7 +87,7 @@ pub fn derive_fury_meta(ast: &syn::DeriveInput, tag: String) -> TokenStream {
     gen.into()
 }
 
-pub fn derive_serialize(ast: &syn::DeriveInput) -> TokenStream {
+pub fn derive_serialize(ast: &syn::DeriveInput, compatible: bool) -> TokenStream {
     let name = &ast.ident;
     let fields = match &ast.data {
         syn::Data::Struct(s) => sorted_fields(&s.fields),
@@ -72,15 +96,40 @@ pub fn derive_serialize(ast: &syn::DeriveInput) -> TokenStream {
         }
     };
 
-    let accessor_exprs = fields.iter().map(|field| {
+    let accessor_exprs = fields.iter().map(|(member, field)| {
+        let ty = &field.ty;
+        quote! {
+            <#ty as fury::__derive::Serialize>::serialize_field(&self.#member, serializer);
+        }
+    });
+
+    let size_exprs = fields.iter().map(|(member, field)| {
+        let ty = &field.ty;
+        quote! {
+            <#ty as fury::__derive::Serialize>::field_size(&self.#member, serializer)
+        }
+    });
+
+    let compatible_accessor_exprs = fields.iter().map(|(member, field)| {
+        let ty = &field.ty;
+        let name = field_name(member);
+        quote! {
+            serializer.write_field_name(#name);
+            <#ty as fury::__derive::Serialize>::serialize(&self.#member, serializer);
+        }
+    });
+
+    let compatible_size_exprs = fields.iter().map(|(member, field)| {
         let ty = &field.ty;
-        let ident = &field.ident;
+        let name = field_name(member);
         quote! {
-            <#ty as fury::__derive::Serialize>::serialize(&self.#ident, serializer);
+            serializer.field_name_size(#name) + <#ty as fury::__derive::Serialize>::serialized_size(&self.#member, serializer)
         }
     });
 
-    let reserved_size_exprs = fields.iter().map(|field| {
+    let num_fields = fields.len() as u32;
+
+    let reserved_size_exprs = fields.iter().map(|(_, field)| {
         let ty = &field.ty;
         // each field have one byte ref tag and two byte type id
         quote! {
@@ -95,22 +144,38 @@ pub fn derive_serialize(ast: &syn::DeriveInput) -> TokenStream {
             fn write(&self, serializer: &mut fury::__derive::SerializerState) {
                 // write tag string
                 serializer.write_tag(<#name as fury::__derive::FuryMeta>::tag());
-                // write tag hash
-                serializer.writer.u32(<#name as fury::__derive::FuryMeta>::hash());
-                // write fields
-                #(#accessor_exprs)*
+                if #compatible || serializer.config.compatible {
+                    // a zero hash marks the compatible layout, every field is written with its name
+                    serializer.writer.u32(0);
+                    serializer.writer.var_uint32(#num_fields);
+                    #(#compatible_accessor_exprs)*
+                } else {
+                    // write tag hash
+                    serializer.writer.u32(<#name as fury::__derive::FuryMeta>::hash());
+                    // write fields
+                    #(#accessor_exprs)*
+                }
+            }
+
+            fn size(&self, serializer: &mut fury::__derive::SerializerState) -> usize {
+                // tag, four byte hash and the fields
+                serializer.tag_size(<#name as fury::__derive::FuryMeta>::tag()) + 4 + if #compatible || serializer.config.compatible {
+                    fury::__derive::Writer::var_uint32_size(#num_fields) #(+ #compatible_size_exprs)*
+                } else {
+                    0 #(+ #size_exprs)*
+                }
             }
 
             fn reserved_space() -> usize {
                 // struct have four byte hash
-                #tag_bytelen + 4 + #(#reserved_size_exprs)+*
+                #tag_bytelen + 4 #(+ #reserved_size_exprs)*
             }
         }
     };
     gen.into()
 }
 
-pub fn derive_deserilize(ast: &syn::DeriveInput) -> TokenStream {
+pub fn derive_deserilize(ast: &syn::DeriveInput, compatible: bool, default: bool) -> TokenStream {
     let name = &ast.ident;
     let fields = match &ast.data {
         syn::Data::Struct(s) => sorted_fields(&s.fields),
@@ -119,13 +184,84 @@ pub fn derive_deserilize(ast: &syn::DeriveInput) -> TokenStream {
         }
     };
 
-    let exprs = fields.iter().map(|field| {
+    let exprs = fields.iter().map(|(member, field)| {
+        let ty = &field.ty;
+        quote! {
+            #member: <#ty as fury::__derive::Deserialize>::deserialize_field(deserializer)?
+        }
+    });
+
+    let slots: Vec<_> = fields
+        .iter()
+        .map(|(member, _)| format_ident!("__{}", field_name(member)))
+        .collect();
+
+    let slot_exprs = fields.iter().zip(slots.iter()).map(|((_, field), slot)| {
         let ty = &field.ty;
-        let ident = &field.ident;
         quote! {
-            #ident: <#ty as fury::__derive::Deserialize>::deserialize(deserializer)?
+            let mut #slot: Option<#ty> = None;
+        }
+    });
+
+    let name_exprs = fields.iter().enumerate().map(|(i, (member, _))| {
+        let lit = syn::LitByteStr::new(field_name(member).as_bytes(), name.span());
+        quote! {
+            #lit => #i
+        }
+    });
+
+    let field_exprs = fields
+        .iter()
+        .zip(slots.iter())
+        .enumerate()
+        .map(|(i, ((_, field), slot))| {
+            let ty = &field.ty;
+            quote! {
+                #i => #slot = Some(<#ty as fury::__derive::Deserialize>::deserialize(deserializer)?)
+            }
+        });
+
+    let default_exprs = fields.iter().zip(slots.iter()).map(|((member, _), slot)| {
+        quote! {
+            if let Some(value) = #slot {
+                result.#member = value;
+            }
+        }
+    });
+    let default_expr = quote! {
+        #[allow(unused_mut)]
+        let mut result = <Self as Default>::default();
+        #(#default_exprs)*
+        Ok(result)
+    };
+
+    let missing_exprs = fields.iter().zip(slots.iter()).map(|((member, _), slot)| {
+        let name = field_name(member);
+        quote! {
+            #member: #slot.ok_or(fury::__derive::Error::MissingField(#name))?
         }
     });
+    let missing_expr = quote! {
+        Ok(Self {
+            #(#missing_exprs),*
+        })
+    };
+
+    // the missing fields are taken from Default if the struct is compatible or the config is,
+    // the struct must opt in with #[fury(default)] for the latter
+    let result_expr = if compatible {
+        default_expr
+    } else if default {
+        quote! {
+            if deserializer.config.compatible {
+                #default_expr
+            } else {
+                #missing_expr
+            }
+        }
+    } else {
+        missing_expr
+    };
 
     let gen = quote! {
         impl<'de> fury::__derive::Deserialize for #name {
@@ -133,9 +269,24 @@ pub fn derive_deserilize(ast: &syn::DeriveInput) -> TokenStream {
                 // read tag string
                 deserializer.read_tag()?;
                 // read tag hash
-                let hash = deserializer.reader.u32();
+                let hash = deserializer.reader.u32()?;
                 let expected = <#name as fury::__derive::FuryMeta>::hash();
-                if(hash != expected) {
+                if hash == 0 {
+                    // compatible layout, the fields are matched by name and the unknown ones are skipped
+                    #(#slot_exprs)*
+                    for _ in 0..deserializer.reader.var_uint32()? {
+                        let len = deserializer.reader.var_uint32()?;
+                        let idx = match deserializer.reader.bytes(len as usize)? {
+                            #(#name_exprs,)*
+                            _ => usize::MAX,
+                        };
+                        match idx {
+                            #(#field_exprs,)*
+                            _ => deserializer.skip_value()?,
+                        }
+                    }
+                    #result_expr
+                } else if(hash != expected) {
                     Err(fury::__derive::Error::StructHash{ expected, actial: hash })
                 } else {
                     Ok(Self {
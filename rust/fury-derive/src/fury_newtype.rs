@@ -0,0 +1,121 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::Type;
+
+/// Derive a newtype like `struct UserId(u64)`, which is written exactly like the type it wraps.
+///
+/// A Vec of newtypes is written like a Vec of the wrapped type, a primitive array for the primitives.
+pub fn derive_newtype(ast: &syn::DeriveInput, inner: &Type) -> TokenStream {
+    let name = &ast.ident;
+
+    let gen = quote! {
+        impl fury::__derive::FuryMeta for #name {
+            fn tag() -> &'static str {
+                <#inner as fury::__derive::FuryMeta>::tag()
+            }
+
+            fn hash() -> u32 {
+                <#inner as fury::__derive::FuryMeta>::hash()
+            }
+
+            fn ty() -> fury::__derive::FieldType {
+                <#inner as fury::__derive::FuryMeta>::ty()
+            }
+
+            fn vec_ty() -> fury::__derive::FieldType {
+                <#inner as fury::__derive::FuryMeta>::vec_ty()
+            }
+
+            fn field_ty() -> fury::__derive::FieldType {
+                <#inner as fury::__derive::FuryMeta>::field_ty()
+            }
+        }
+
+        impl fury::__derive::Serialize for #name {
+            fn write(&self, serializer: &mut fury::__derive::SerializerState) {
+                <#inner as fury::__derive::Serialize>::write(&self.0, serializer);
+            }
+
+            fn write_vec(value: &[Self], serializer: &mut fury::__derive::SerializerState) {
+                // Self only wraps the inner type
+                let value = unsafe { fury::__derive::newtype_slice::<Self, #inner>(value) };
+                <#inner as fury::__derive::Serialize>::write_vec(value, serializer);
+            }
+
+            fn size(&self, serializer: &mut fury::__derive::SerializerState) -> usize {
+                <#inner as fury::__derive::Serialize>::size(&self.0, serializer)
+            }
+
+            fn vec_size(value: &[Self], serializer: &mut fury::__derive::SerializerState) -> usize {
+                let value = unsafe { fury::__derive::newtype_slice::<Self, #inner>(value) };
+                <#inner as fury::__derive::Serialize>::vec_size(value, serializer)
+            }
+
+            fn reserved_space() -> usize {
+                <#inner as fury::__derive::Serialize>::reserved_space()
+            }
+
+            fn is_null(&self) -> bool {
+                <#inner as fury::__derive::Serialize>::is_null(&self.0)
+            }
+
+            fn serialize_field(&self, serializer: &mut fury::__derive::SerializerState) {
+                <#inner as fury::__derive::Serialize>::serialize_field(&self.0, serializer);
+            }
+
+            fn field_size(&self, serializer: &mut fury::__derive::SerializerState) -> usize {
+                <#inner as fury::__derive::Serialize>::field_size(&self.0, serializer)
+            }
+
+            fn serialized_size(&self, serializer: &mut fury::__derive::SerializerState) -> usize {
+                <#inner as fury::__derive::Serialize>::serialized_size(&self.0, serializer)
+            }
+
+            fn serialize(&self, serializer: &mut fury::__derive::SerializerState) {
+                <#inner as fury::__derive::Serialize>::serialize(&self.0, serializer);
+            }
+        }
+
+        impl fury::__derive::Deserialize for #name {
+            fn read(deserializer: &mut fury::__derive::DeserializerState) -> Result<Self, fury::__derive::Error> {
+                <#inner as fury::__derive::Deserialize>::read(deserializer).map(Self)
+            }
+
+            fn read_vec(deserializer: &mut fury::__derive::DeserializerState) -> Result<Vec<Self>, fury::__derive::Error> {
+                <#inner as fury::__derive::Deserialize>::read_vec(deserializer)
+                    .map(|value| value.into_iter().map(Self).collect())
+            }
+
+            fn read_with_type(deserializer: &mut fury::__derive::DeserializerState, type_id: i16) -> Result<Self, fury::__derive::Error> {
+                <#inner as fury::__derive::Deserialize>::read_with_type(deserializer, type_id).map(Self)
+            }
+
+            fn null() -> Result<Self, fury::__derive::Error> {
+                <#inner as fury::__derive::Deserialize>::null().map(Self)
+            }
+
+            fn deserialize_field(deserializer: &mut fury::__derive::DeserializerState) -> Result<Self, fury::__derive::Error> {
+                <#inner as fury::__derive::Deserialize>::deserialize_field(deserializer).map(Self)
+            }
+
+            fn deserialize(deserializer: &mut fury::__derive::DeserializerState) -> Result<Self, fury::__derive::Error> {
+                <#inner as fury::__derive::Deserialize>::deserialize(deserializer).map(Self)
+            }
+        }
+    };
+    gen.into()
+}
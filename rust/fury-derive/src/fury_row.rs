@@ -26,7 +26,7 @@ pub fn derive_row(ast: &syn::DeriveInput) -> TokenStream {
         }
     };
 
-    let write_exprs = fields.iter().enumerate().map(|(index, field)| {
+    let write_exprs = fields.iter().enumerate().map(|(index, (_, field))| {
         let ty = &field.ty;
         let ident = field.ident.as_ref().expect("field should provide ident");
 
@@ -37,7 +37,7 @@ pub fn derive_row(ast: &syn::DeriveInput) -> TokenStream {
         }
     });
 
-    let getter_exprs = fields.iter().enumerate().map(|(index, field)| {
+    let getter_exprs = fields.iter().enumerate().map(|(index, (_, field))| {
         let ty = &field.ty;
         let ident = field.ident.as_ref().expect("field should provide ident");
         let getter_name: proc_macro2::Ident = syn::Ident::new(&format!("{}", ident), ident.span());
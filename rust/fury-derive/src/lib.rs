@@ -12,22 +12,43 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use fury_enum::derive_enum;
 use fury_meta::{derive_deserilize, derive_fury_meta, derive_serialize};
+use fury_newtype::derive_newtype;
 use fury_row::derive_row;
 use proc_macro::TokenStream;
 use syn::{parse_macro_input, DeriveInput};
 
+mod fury_enum;
 mod fury_meta;
+mod fury_newtype;
 mod fury_row;
 
-#[proc_macro_derive(Fury, attributes(tag))]
+/// Derive the serialization of a struct, which is written as `#[tag("...")]`.
+///
+/// A struct with `#[compatible]` is written with the names of its fields, so the reader may have more or fewer fields,
+/// the missing ones are taken from `Default`. `Config::compatible` writes every struct this way,
+/// a struct without `#[compatible]` takes the missing fields from `Default` under that config
+/// if it derives `Default` and opts in with `#[fury(default)]`, they are an error otherwise.
+///
+/// An enum whose variants have no fields is written like `EnumSerializer` in Java, with the enum type id
+/// and the ordinal of the variant, or its name with `#[by_name]`. Variants may carry fields,
+/// the enum is written with its tag then, followed by the variant and its fields.
+///
+/// Tuple structs and unit structs are written like structs, their fields are named by index.
+/// A tuple struct with a single field and no tag is a newtype, it is written exactly like the field.
+#[proc_macro_derive(Fury, attributes(tag, compatible, by_name, fury))]
 pub fn proc_macro_derive_fury_meta(input: proc_macro::TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let tag = input
-        .attrs
-        .iter()
-        .find(|attr| attr.path().is_ident("tag"))
-        .expect("should have tag");
+    let tag = input.attrs.iter().find(|attr| attr.path().is_ident("tag"));
+    if let (None, syn::Data::Struct(data)) = (tag, &input.data) {
+        if let syn::Fields::Unnamed(fields) = &data.fields {
+            if fields.unnamed.len() == 1 {
+                return derive_newtype(&input, &fields.unnamed[0].ty);
+            }
+        }
+    }
+    let tag = tag.expect("should have tag");
     let expr: syn::ExprLit = tag.parse_args().expect("should tag contain string value");
     let tag = match expr.lit {
         syn::Lit::Str(s) => s.value(),
@@ -35,11 +56,41 @@ pub fn proc_macro_derive_fury_meta(input: proc_macro::TokenStream) -> TokenStrea
             panic!("tag should be string")
         }
     };
+    // always write the compatible layout and fill the missing fields from Default
+    let compatible = input
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("compatible"));
+    if let syn::Data::Enum(data) = &input.data {
+        assert!(!compatible, "compatible is only supported for structs");
+        let by_name = input
+            .attrs
+            .iter()
+            .any(|attr| attr.path().is_ident("by_name"));
+        return derive_enum(&input, data, tag, by_name);
+    }
+    // fill the missing fields from Default when the config is compatible
+    let mut default = false;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("fury"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("default") {
+                default = true;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported fury attribute"))
+            }
+        })
+        .expect("should fury attribute be valid");
+    }
     let mut token_stream = derive_fury_meta(&input, tag);
     // append serialize impl
-    token_stream.extend(derive_serialize(&input));
+    token_stream.extend(derive_serialize(&input, compatible));
     // append deserialize impl
-    token_stream.extend(derive_deserilize(&input));
+    token_stream.extend(derive_deserilize(&input, compatible, default));
     token_stream
 }
 
@@ -12,56 +12,233 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{
-    mem, ptr,
-    slice::{from_raw_parts, from_raw_parts_mut},
-};
+use std::{io, mem};
 
 use byteorder::{ByteOrder, LittleEndian};
 
-#[derive(Default)]
-pub struct Writer {
-    bf: Vec<u8>,
-    reserved: usize,
+use crate::error::Error;
+
+const HALF_MAX_INT_VALUE: i64 = (i32::MAX / 2) as i64;
+const HALF_MIN_INT_VALUE: i64 = (i32::MIN / 2) as i64;
+const BIG_LONG_FLAG: u8 = 0b1;
+
+/// Size of the chunks which a writer with a sink flushes.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Writes the primitives of the fury protocol into a growable buffer or a fixed slice.
+///
+/// A writer created by `with_sink` keeps at most about `CHUNK_SIZE` bytes in memory and
+/// flushes the rest to the sink while writing. Offsets passed to `set_bytes` are counted
+/// from the first byte ever written, bytes which are already flushed can't be set again.
+///
+/// A writer created from a `&mut [u8]` never allocates. Bytes which don't fit into the
+/// slice are counted but dropped, and `flush` reports them as [`Error::BufferFull`].
+pub struct Writer<'w> {
+    bf: Storage<'w>,
+    sink: Option<&'w mut dyn io::Write>,
+    // the buffer is flushed to the sink once it holds this many bytes
+    flush_threshold: usize,
+    // number of bytes which were flushed to the sink
+    flushed: usize,
+    error: Option<Error>,
+}
+
+enum Storage<'w> {
+    Vec(Vec<u8>),
+    // len keeps counting past the end of the slice, so offsets stay right after it is full
+    Slice { bf: &'w mut [u8], len: usize },
+}
+
+impl Storage<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Storage::Vec(bf) => bf.len(),
+            Storage::Slice { len, .. } => *len,
+        }
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            Storage::Vec(bf) => bf,
+            Storage::Slice { bf, len } => &bf[..(*len).min(bf.len())],
+        }
+    }
+
+    fn extend_from_slice(&mut self, v: &[u8]) {
+        match self {
+            Storage::Vec(bf) => bf.extend_from_slice(v),
+            Storage::Slice { bf, len } => {
+                if let Some(dst) = bf.get_mut(*len..*len + v.len()) {
+                    dst.copy_from_slice(v);
+                }
+                *len += v.len();
+            }
+        }
+    }
+
+    fn extend_zeroed(&mut self, additional: usize) {
+        match self {
+            Storage::Vec(bf) => bf.resize(bf.len() + additional, 0),
+            Storage::Slice { bf, len } => {
+                if let Some(dst) = bf.get_mut(*len..*len + additional) {
+                    dst.fill(0);
+                }
+                *len += additional;
+            }
+        }
+    }
+
+    fn set(&mut self, offset: usize, data: &[u8]) {
+        match self {
+            Storage::Vec(bf) => bf
+                .get_mut(offset..offset + data.len())
+                .expect("should set bytes which were written")
+                .copy_from_slice(data),
+            Storage::Slice { bf, len } => {
+                assert!(
+                    offset + data.len() <= *len,
+                    "should set bytes which were written"
+                );
+                // the bytes past the slice were dropped
+                if let Some(dst) = bf.get_mut(offset..offset + data.len()) {
+                    dst.copy_from_slice(data);
+                }
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        match self {
+            Storage::Vec(bf) => bf.clear(),
+            Storage::Slice { len, .. } => *len = 0,
+        }
+    }
+}
+
+impl Default for Writer<'_> {
+    fn default() -> Self {
+        Writer {
+            bf: Storage::Vec(Vec::new()),
+            sink: None,
+            flush_threshold: usize::MAX,
+            flushed: 0,
+            error: None,
+        }
+    }
+}
+
+/// Writes after the bytes already in the vec, the vec can be taken back by `into_inner`.
+impl From<Vec<u8>> for Writer<'_> {
+    fn from(bf: Vec<u8>) -> Self {
+        Writer {
+            bf: Storage::Vec(bf),
+            ..Default::default()
+        }
+    }
+}
+
+/// Writes from the start of the slice without allocating, `len` is the number of bytes written.
+impl<'w> From<&'w mut [u8]> for Writer<'w> {
+    fn from(bf: &'w mut [u8]) -> Self {
+        Writer {
+            bf: Storage::Slice { bf, len: 0 },
+            ..Default::default()
+        }
+    }
 }
 
 macro_rules! write_num {
     ($name: ident, $ty: tt) => {
         pub fn $name(&mut self, v: $ty) {
-            let c = self.cast::<$ty>(mem::size_of::<$ty>());
-            c[0] = v;
-            self.move_next(mem::size_of::<$ty>());
+            self.bf.extend_from_slice(&v.to_le_bytes());
+            self.flush_if_full();
         }
     };
 }
 
-impl Writer {
+impl<'w> Writer<'w> {
+    pub fn with_sink(sink: &'w mut dyn io::Write) -> Writer<'w> {
+        Writer {
+            bf: Storage::Vec(Vec::with_capacity(CHUNK_SIZE)),
+            sink: Some(sink),
+            flush_threshold: CHUNK_SIZE,
+            ..Default::default()
+        }
+    }
+
     pub fn dump(&self) -> Vec<u8> {
-        self.bf.clone()
+        self.as_slice().to_vec()
+    }
+
+    /// The buffered bytes, which are all the written bytes unless the writer has a sink or a full slice.
+    pub fn as_slice(&self) -> &[u8] {
+        self.bf.as_slice()
     }
 
+    /// Takes the buffer out of the writer without copying it, a writer over a slice returns a copy.
+    pub fn into_inner(self) -> Vec<u8> {
+        match self.bf {
+            Storage::Vec(bf) => bf,
+            Storage::Slice { .. } => self.dump(),
+        }
+    }
+
+    /// Empties the writer but keeps its allocation, so it can be reused for the next payload.
+    pub fn clear(&mut self) {
+        self.bf.clear();
+        self.flushed = 0;
+        self.error = None;
+    }
+
+    /// Number of bytes written so far, including the flushed ones.
     pub fn len(&self) -> usize {
-        self.bf.len()
+        self.flushed + self.bf.len()
     }
 
-    fn move_next(&mut self, additional: usize) {
-        unsafe { self.bf.set_len(self.bf.len() + additional) }
+    pub fn reserve(&mut self, additional: usize) {
+        // the buffer of a streaming writer never grows beyond a chunk
+        if let (Storage::Vec(bf), None) = (&mut self.bf, &self.sink) {
+            bf.reserve(additional);
+        }
     }
-    fn ptr(&mut self) -> *mut u8 {
-        unsafe {
-            let t = self.bf.as_mut_ptr();
-            t.add(self.bf.len())
+
+    fn flush_if_full(&mut self) {
+        if self.bf.len() >= self.flush_threshold {
+            self.flush_buffer();
         }
     }
 
-    fn cast<T>(&mut self, len: usize) -> &mut [T] {
-        unsafe { from_raw_parts_mut(self.ptr() as *mut T, len) }
+    fn flush_buffer(&mut self) {
+        if let Some(sink) = self.sink.as_mut() {
+            if self.error.is_none() {
+                if let Err(e) = sink.write_all(self.bf.as_slice()) {
+                    self.error = Some(Error::Io(e));
+                }
+            }
+            self.flushed += self.bf.len();
+            self.bf.clear();
+        }
     }
 
-    pub fn reserve(&mut self, additional: usize) {
-        self.reserved += additional;
-        if self.bf.capacity() < self.reserved {
-            self.bf.reserve(self.reserved);
+    /// Flushes the buffered bytes and the sink.
+    ///
+    /// The first error of the writer is returned here, including io errors of the flushes while writing.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.flush_buffer();
+        if let (Some(sink), None) = (self.sink.as_mut(), &self.error) {
+            sink.flush()?;
+        }
+        if let Storage::Slice { bf, len } = &self.bf {
+            if *len > bf.len() {
+                return Err(Error::BufferFull {
+                    needed: *len,
+                    capacity: bf.len(),
+                });
+            }
+        }
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
         }
     }
 
@@ -75,97 +252,246 @@ impl Writer {
     write_num!(i64, i64);
 
     pub fn skip(&mut self, len: usize) {
-        self.move_next(len);
+        self.bf.extend_zeroed(len);
+        self.flush_if_full();
     }
 
     pub fn f32(&mut self, value: f32) {
-        LittleEndian::write_f32(self.cast::<u8>(4), value);
-        self.move_next(4);
+        self.bf.extend_from_slice(&value.to_le_bytes());
+        self.flush_if_full();
     }
 
     pub fn f64(&mut self, value: f64) {
-        LittleEndian::write_f64(self.cast::<u8>(8), value);
-        self.move_next(8);
+        self.bf.extend_from_slice(&value.to_le_bytes());
+        self.flush_if_full();
+    }
+
+    /// Writes an unsigned varint of 1~5 bytes, the highest bit of every byte flags whether there is a next byte.
+    pub fn var_uint32(&mut self, mut value: u32) {
+        while value >= 0x80 {
+            self.u8((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.u8(value as u8);
     }
 
+    /// Writes a zigzag encoded varint of 1~5 bytes, so small negative numbers take few bytes too.
     pub fn var_int32(&mut self, value: i32) {
-        if value >> 7 == 0 {
-            self.u8(value as u8);
-        } else if value >> 14 == 0 {
-            let u1 = (value & 0x7F) | 0x80;
-            let u2 = value >> 7;
-            self.u16(((u1 << 8) | u2) as u16);
-        } else if value >> 21 == 0 {
-            let u1 = (value & 0x7F) | 0x80;
-            let u2 = value >> 7 | 0x80;
-            self.u16(((u1 << 8) | u2) as u16);
-            self.u8((value >> 14) as u8);
-        } else if value >> 28 == 0 {
-            let u1 = (value & 0x7F) | 0x80;
-            let u2 = value >> 7 | 0x80;
-            let u3 = value >> 14 | 0x80;
-            let u4 = value >> 21 | 0x80;
-            self.u32(((u1 << 24) | (u2 << 16) | (u3 << 8) | u4) as u32);
+        self.var_uint32(((value << 1) ^ (value >> 31)) as u32);
+    }
+
+    /// Writes an unsigned varint of 1~9 bytes.
+    /// The first 8 bytes carry 7 bits each, the 9th byte carries the remaining 8 bits.
+    pub fn var_uint64(&mut self, mut value: u64) {
+        for _ in 0..8 {
+            if value < 0x80 {
+                self.u8(value as u8);
+                return;
+            }
+            self.u8((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.u8(value as u8);
+    }
+
+    /// Writes a zigzag encoded varint of 1~9 bytes.
+    pub fn var_int64(&mut self, value: i64) {
+        self.var_uint64(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    /// Writes a small long as int.
+    /// A value in [-1073741824, 1073741823] is written as 4 bytes `value << 1`,
+    /// otherwise as one byte flag `0b1` followed by the 8 bytes value.
+    pub fn sli_int64(&mut self, value: i64) {
+        if (HALF_MIN_INT_VALUE..=HALF_MAX_INT_VALUE).contains(&value) {
+            self.i32((value as i32) << 1);
+        } else {
+            self.u8(BIG_LONG_FLAG);
+            self.i64(value);
+        }
+    }
+
+    /// Number of bytes `var_uint32` writes for the value.
+    pub fn var_uint32_size(value: u32) -> usize {
+        // every byte carries 7 bits, zero takes one byte too
+        (32 - (value | 1).leading_zeros() as usize + 6) / 7
+    }
+
+    /// Number of bytes `var_int32` writes for the value.
+    pub fn var_int32_size(value: i32) -> usize {
+        Self::var_uint32_size(((value << 1) ^ (value >> 31)) as u32)
+    }
+
+    /// Number of bytes `var_uint64` writes for the value.
+    pub fn var_uint64_size(value: u64) -> usize {
+        let bits = 64 - (value | 1).leading_zeros() as usize;
+        if bits > 56 {
+            9
         } else {
-            let u1 = (value & 0x7F) | 0x80;
-            let u2 = value >> 7 | 0x80;
-            let u3 = value >> 14 | 0x80;
-            let u4 = value >> 21 | 0x80;
-            self.u32(((u1 << 24) | (u2 << 16) | (u3 << 8) | u4) as u32);
-            self.u8((value >> 28) as u8);
+            (bits + 6) / 7
+        }
+    }
+
+    /// Number of bytes `var_int64` writes for the value.
+    pub fn var_int64_size(value: i64) -> usize {
+        Self::var_uint64_size(((value << 1) ^ (value >> 63)) as u64)
+    }
+
+    /// Number of bytes `sli_int64` writes for the value.
+    pub fn sli_int64_size(value: i64) -> usize {
+        if (HALF_MIN_INT_VALUE..=HALF_MAX_INT_VALUE).contains(&value) {
+            4
+        } else {
+            9
         }
     }
 
     pub fn bytes(&mut self, v: &[u8]) {
-        self.reserve(v.len());
-        unsafe {
-            ptr::copy_nonoverlapping(v.as_ptr(), self.ptr(), v.len());
+        if v.len() >= self.flush_threshold {
+            // big slices go to the sink directly instead of being copied into the buffer
+            self.flush_buffer();
+            if let (Some(sink), None) = (self.sink.as_mut(), &self.error) {
+                if let Err(e) = sink.write_all(v) {
+                    self.error = Some(Error::Io(e));
+                }
+            }
+            self.flushed += v.len();
+            return;
         }
-        self.move_next(v.len());
+        self.bf.extend_from_slice(v);
+        self.flush_if_full();
     }
 
+    /// Overwrites bytes which were written before, used by the row format to fill in offsets and sizes.
+    ///
+    /// Bytes which are already flushed to the sink can't be set, the error is returned by `flush`.
     pub fn set_bytes(&mut self, offset: usize, data: &[u8]) {
-        self.bf
-            .get_mut(offset..offset + data.len())
-            .expect("//todo")
-            .copy_from_slice(data);
+        if offset < self.flushed {
+            if self.error.is_none() {
+                self.error = Some(Error::Flushed {
+                    offset,
+                    flushed: self.flushed,
+                });
+            }
+            return;
+        }
+        self.bf.set(offset - self.flushed, data);
     }
 }
 
-pub struct Reader<'de> {
-    bf: &'de [u8],
+/// Reads the primitives of the fury protocol from a byte slice or a `BufRead` source.
+///
+/// Every read is bounds-checked and fails with [`Error::UnexpectedEof`] instead of
+/// reading past the end of the slice, so truncated or malicious payloads are safe to
+/// feed in. The unchecked fast paths are only taken once the length was verified.
+///
+/// A reader created by `with_source` refills its buffer from the source on demand. It
+/// takes exactly the bytes it reads from the source, so the bytes after a payload are
+/// left in the source for the next one.
+pub struct Reader<'bf> {
+    bf: Buffer<'bf>,
     cursor: usize,
+    source: Option<&'bf mut dyn io::BufRead>,
+}
+
+enum Buffer<'bf> {
+    Borrowed(&'bf [u8]),
+    // the window of a streaming reader, the bytes before the cursor are dropped on refill
+    Owned(Vec<u8>),
+    #[cfg(feature = "bytes")]
+    Shared(&'bf bytes::Bytes),
 }
 
 macro_rules! read_num {
     ($name: ident, $ty: tt) => {
-        pub fn $name(&mut self) -> $ty {
-            let c = self.cast::<$ty>(mem::size_of::<$ty>());
-            let result = c[0];
-            self.move_next(mem::size_of::<$ty>());
-            result
+        pub fn $name(&mut self) -> Result<$ty, Error> {
+            let mut bytes = [0; mem::size_of::<$ty>()];
+            bytes.copy_from_slice(self.bytes(mem::size_of::<$ty>())?);
+            Ok($ty::from_le_bytes(bytes))
         }
     };
 }
 
 impl<'bf> Reader<'bf> {
     pub fn new(bf: &[u8]) -> Reader {
-        Reader { bf, cursor: 0 }
+        Reader {
+            bf: Buffer::Borrowed(bf),
+            cursor: 0,
+            source: None,
+        }
     }
 
-    fn move_next(&mut self, additional: usize) {
-        self.cursor += additional;
+    pub fn with_source(source: &'bf mut dyn io::BufRead) -> Reader<'bf> {
+        Reader {
+            bf: Buffer::Owned(Vec::new()),
+            cursor: 0,
+            source: Some(source),
+        }
+    }
+
+    /// Read from `Bytes`, binaries read by `shared_bytes` then share its allocation.
+    #[cfg(feature = "bytes")]
+    pub fn from_bytes(bf: &'bf bytes::Bytes) -> Reader<'bf> {
+        Reader {
+            bf: Buffer::Shared(bf),
+            cursor: 0,
+            source: None,
+        }
     }
 
-    fn ptr(&self) -> *const u8 {
-        unsafe {
-            let t = self.bf.as_ptr();
-            t.add(self.cursor)
+    fn buf(&self) -> &[u8] {
+        match &self.bf {
+            Buffer::Borrowed(bf) => bf,
+            Buffer::Owned(bf) => bf,
+            #[cfg(feature = "bytes")]
+            Buffer::Shared(bf) => bf,
         }
     }
 
-    fn cast<T>(&self, len: usize) -> &[T] {
-        unsafe { from_raw_parts(self.ptr() as *const T, len) }
+    /// Number of bytes which are not read yet.
+    ///
+    /// For a streaming reader, only the bytes which are already taken from the source are counted.
+    pub fn remaining(&self) -> usize {
+        self.buf().len() - self.cursor
+    }
+
+    fn check_bound(&mut self, needed: usize) -> Result<(), Error> {
+        if self.remaining() < needed {
+            self.fill(needed)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Takes bytes from the source until `needed` bytes are remaining.
+    fn fill(&mut self, needed: usize) -> Result<(), Error> {
+        let remaining = self.remaining();
+        let (Some(source), Buffer::Owned(bf)) = (self.source.as_mut(), &mut self.bf) else {
+            return Err(Error::UnexpectedEof { needed, remaining });
+        };
+        bf.drain(..self.cursor);
+        self.cursor = 0;
+        while bf.len() < needed {
+            let available = match source.fill_buf() {
+                Ok(available) => available,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(Error::Io(e)),
+            };
+            if available.is_empty() {
+                return Err(Error::UnexpectedEof {
+                    needed,
+                    remaining: bf.len(),
+                });
+            }
+            let len = available.len().min(needed - bf.len());
+            bf.extend_from_slice(&available[..len]);
+            source.consume(len);
+        }
+        Ok(())
+    }
+
+    fn move_next(&mut self, additional: usize) {
+        self.cursor += additional;
     }
 
     read_num!(u8, u8);
@@ -177,53 +503,96 @@ impl<'bf> Reader<'bf> {
     read_num!(i32, i32);
     read_num!(i64, i64);
 
-    pub fn f32(&mut self) -> f32 {
-        let result = LittleEndian::read_f32(self.cast::<u8>(4));
-        self.move_next(4);
-        result
-    }
-
-    pub fn f64(&mut self) -> f64 {
-        let result = LittleEndian::read_f64(self.cast::<u8>(8));
-        self.move_next(8);
-        result
-    }
-
-    pub fn var_int32(&mut self) -> i32 {
-        let mut byte_ = self.i8() as i32;
-        let mut result = byte_ & 0x7F;
-        if (byte_ & 0x80) != 0 {
-            byte_ = self.i8() as i32;
-            result |= (byte_ & 0x7F) << 7;
-            if (byte_ & 0x80) != 0 {
-                byte_ = self.i8() as i32;
-                result |= (byte_ & 0x7F) << 14;
-                if (byte_ & 0x80) != 0 {
-                    byte_ = self.i8() as i32;
-                    result |= (byte_ & 0x7F) << 21;
-                    if (byte_ & 0x80) != 0 {
-                        byte_ = self.i8() as i32;
-                        result |= (byte_ & 0x7F) << 28;
-                    }
-                }
+    /// The next byte as i8, without moving past it.
+    pub fn peek_i8(&mut self) -> Result<i8, Error> {
+        self.check_bound(1)?;
+        Ok(self.buf()[self.cursor] as i8)
+    }
+
+    pub fn f32(&mut self) -> Result<f32, Error> {
+        Ok(LittleEndian::read_f32(self.bytes(4)?))
+    }
+
+    pub fn f64(&mut self) -> Result<f64, Error> {
+        Ok(LittleEndian::read_f64(self.bytes(8)?))
+    }
+
+    pub fn var_uint32(&mut self) -> Result<u32, Error> {
+        let mut result = 0;
+        for i in 0..5 {
+            let byte_ = self.u8()?;
+            result |= ((byte_ & 0x7F) as u32) << (i * 7);
+            if (byte_ & 0x80) == 0 {
+                break;
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn var_int32(&mut self) -> Result<i32, Error> {
+        let value = self.var_uint32()?;
+        Ok(((value >> 1) as i32) ^ -((value & 1) as i32))
+    }
+
+    pub fn var_uint64(&mut self) -> Result<u64, Error> {
+        let mut result = 0;
+        for i in 0..8 {
+            let byte_ = self.u8()?;
+            result |= ((byte_ & 0x7F) as u64) << (i * 7);
+            if (byte_ & 0x80) == 0 {
+                return Ok(result);
             }
         }
-        result
+        Ok(result | (self.u8()? as u64) << 56)
     }
 
-    pub fn string(&mut self, len: u32) -> String {
-        let result = String::from_utf8_lossy(self.cast::<u8>(len as usize)).to_string();
-        self.move_next(len as usize);
-        result
+    pub fn var_int64(&mut self) -> Result<i64, Error> {
+        let value = self.var_uint64()?;
+        Ok(((value >> 1) as i64) ^ -((value & 1) as i64))
+    }
+
+    pub fn sli_int64(&mut self) -> Result<i64, Error> {
+        self.check_bound(1)?;
+        if self.buf()[self.cursor] & BIG_LONG_FLAG == 0 {
+            Ok((self.i32()? >> 1) as i64)
+        } else {
+            self.move_next(1);
+            self.i64()
+        }
     }
 
-    pub fn skip(&mut self, len: u32) {
-        self.move_next(len as usize);
+    /// Read `len` bytes of utf8, invalid utf8 gives `Error::Utf8`.
+    pub fn string(&mut self, len: usize) -> Result<String, Error> {
+        Ok(std::str::from_utf8(self.bytes(len)?)?.to_string())
+    }
+
+    pub fn skip(&mut self, len: usize) -> Result<(), Error> {
+        self.check_bound(len)?;
+        self.move_next(len);
+        Ok(())
+    }
+
+    /// The next bytes, without moving past them.
+    pub fn peek_bytes(&mut self, len: usize) -> Result<&[u8], Error> {
+        self.check_bound(len)?;
+        Ok(&self.buf()[self.cursor..self.cursor + len])
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&[u8], Error> {
+        self.check_bound(len)?;
+        self.move_next(len);
+        Ok(&self.buf()[self.cursor - len..self.cursor])
     }
 
-    pub fn bytes(&mut self, len: usize) -> &'bf [u8] {
-        let result = &self.bf[self.cursor..self.cursor + len];
+    /// Like `bytes`, but the result shares the allocation when reading from `Bytes`, it is copied otherwise.
+    #[cfg(feature = "bytes")]
+    pub fn shared_bytes(&mut self, len: usize) -> Result<bytes::Bytes, Error> {
+        self.check_bound(len)?;
+        let start = self.cursor;
         self.move_next(len);
-        result
+        Ok(match &self.bf {
+            Buffer::Shared(bf) => bf.slice(start..self.cursor),
+            _ => bytes::Bytes::copy_from_slice(&self.buf()[start..self.cursor]),
+        })
     }
 }
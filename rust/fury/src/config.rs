@@ -0,0 +1,63 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use crate::registry::Registry;
+
+/// Encoding of `i64` values when `Config::compress_long` is enabled, the same as `LongEncoding` in Java.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LongEncoding {
+    /// Small long as int: 4 bytes for values in [-1073741824, 1073741823], 9 bytes otherwise.
+    #[default]
+    SLI,
+    /// Progressive variable-length long: zigzag varint of 1~9 bytes.
+    PVL,
+}
+
+/// Options of the serialization.
+///
+/// Both sides must use the same config, it isn't written into the payload.
+/// The default config writes every number with fixed width, which is what pyfury does in xlang mode.
+/// ```
+/// use fury::{Config, LongEncoding};
+///
+/// let config = Config {
+///     compress_int: true,
+///     compress_long: true,
+///     long_encoding: LongEncoding::PVL,
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    /// Write `i32` as zigzag varint, the same as `FuryBuilder#withIntCompressed` in Java.
+    pub compress_int: bool,
+    /// Write `i64` with `long_encoding`, the same as `FuryBuilder#withLongCompressed` in Java.
+    pub compress_long: bool,
+    pub long_encoding: LongEncoding,
+    /// Write strings as latin1, utf16 or utf8 with a coder byte, whichever is the most compact,
+    /// the same as `FuryBuilder#withStringCompressed` in Java. Strings are plain utf8 otherwise.
+    pub compress_string: bool,
+    /// Write every derived struct in the compatible layout, as if all of them had `#[compatible]`.
+    ///
+    /// A struct with `#[fury(default)]` takes the fields missing from the payload from `Default` under this config,
+    /// like a struct with `#[compatible]` always does.
+    pub compatible: bool,
+    /// Write the native protocol of Rust, the same idea as the native mode of Java.
+    ///
+    /// The fields of consistent structs drop their ref flags and type ids, and every Option keeps its own flag
+    /// so they can be nested. Only Rust reads it: the head is marked with `Language::RUST`,
+    /// and the reader follows the head whatever its own config says.
+    pub native: bool,
+    /// The types trait object fields are read as, by their tags.
+    pub registry: Registry,
+}
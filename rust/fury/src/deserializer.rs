@@ -13,20 +13,69 @@
 // limitations under the License.
 
 use super::buffer::Reader;
+use super::config::{Config, LongEncoding};
 use super::types::Language;
 use crate::{
     error::Error,
-    types::{FuryMeta, RefFlag},
+    types::{compute_tag_hash, config_flags, FieldType, FuryMeta, RefFlag, StringFlag},
 };
 use chrono::{DateTime, Days, NaiveDate, NaiveDateTime, TimeZone, Utc};
+#[cfg(feature = "indexmap")]
+use indexmap::{IndexMap, IndexSet};
 use std::{
-    collections::{HashMap, HashSet},
-    mem,
+    any::Any,
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, LinkedList, VecDeque},
+    hash::{BuildHasher, Hash},
+    io, mem,
+    rc::{Rc, Weak},
+    sync::Arc,
 };
 
-fn from_u8_slice<T: Clone>(slice: &[u8]) -> Vec<T> {
-    let byte_len = slice.len() / mem::size_of::<T>();
-    unsafe { std::slice::from_raw_parts(slice.as_ptr().cast::<T>(), byte_len) }.to_vec()
+/// Convert a u8 slice to a typed Vec.
+/// The slice comes from the buffer and may be unaligned, so the bytes are copied into a fresh allocation.
+/// The bytes are taken in native order, so multi-byte numbers must only be read this way on little-endian hosts.
+fn from_u8_slice<T: Copy>(slice: &[u8]) -> Result<Vec<T>, Error> {
+    let size = mem::size_of::<T>();
+    if slice.len() % size != 0 {
+        return Err(Error::ArrayBytes {
+            len: slice.len(),
+            size,
+        });
+    }
+    let len = slice.len() / size;
+    let mut result = Vec::<T>::with_capacity(len);
+    unsafe {
+        std::ptr::copy_nonoverlapping(
+            slice.as_ptr(),
+            result.as_mut_ptr().cast::<u8>(),
+            len * mem::size_of::<T>(),
+        );
+        result.set_len(len);
+    }
+    Ok(result)
+}
+
+/// Read a primitive array of little-endian elements, which are `N` bytes each.
+fn read_le_array<T: Copy, const N: usize>(
+    deserializer: &mut DeserializerState,
+    from_le_bytes: fn([u8; N]) -> T,
+) -> Result<Vec<T>, Error> {
+    let bytes = deserializer.read_buffer()?;
+    if cfg!(target_endian = "little") {
+        from_u8_slice::<T>(bytes)
+    } else {
+        let chunks = bytes.chunks_exact(N);
+        if !chunks.remainder().is_empty() {
+            return Err(Error::ArrayBytes {
+                len: bytes.len(),
+                size: N,
+            });
+        }
+        Ok(chunks
+            .map(|chunk| from_le_bytes(chunk.try_into().unwrap()))
+            .collect())
+    }
 }
 
 pub trait Deserialize
@@ -35,39 +84,63 @@ where
 {
     fn read(deserializer: &mut DeserializerState) -> Result<Self, Error>;
 
+    /// Read an object which is shared by Rc, the Rc must be put into the ref table as `id`.
+    ///
+    /// The default implementation puts it there once the object is read. Types which take part in cycles
+    /// overwrite it to put the Rc there first, so the nested objects can refer back to it.
+    fn read_rc(deserializer: &mut DeserializerState, id: u32) -> Result<Rc<Self>, Error>
+    where
+        Self: 'static,
+    {
+        let value = Rc::new(Self::read(deserializer)?);
+        deserializer.set_ref(id, value.clone());
+        Ok(value)
+    }
+
     fn read_vec(deserializer: &mut DeserializerState) -> Result<Vec<Self>, Error> {
-        // length
-        let len = deserializer.reader.var_int32();
-        // value
-        let mut result = Vec::new();
-        for _ in 0..len {
-            result.push(Self::deserialize(deserializer)?);
+        read_list(deserializer)
+    }
+
+    /// Read the value after its type id, which is checked to be the one Self is written with.
+    ///
+    /// Types which can be read from more than one type id overwrite it.
+    fn read_with_type(deserializer: &mut DeserializerState, type_id: i16) -> Result<Self, Error> {
+        check_type_id::<Self>(type_id)?;
+        Self::read(deserializer)
+    }
+
+    /// Read the type id which follows the ref flag and check it, the counterpart of `Serialize::write_type_id`.
+    fn read_type_id(deserializer: &mut DeserializerState) -> Result<(), Error> {
+        read_type_id::<Self>(deserializer)
+    }
+
+    /// The value of a null, only Option has one.
+    fn null() -> Result<Self, Error> {
+        Err(Error::Null)
+    }
+
+    /// Read a field of a struct with the consistent layout, the counterpart of `Serialize::serialize_field`.
+    fn deserialize_field(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+        if deserializer.native {
+            Self::read(deserializer)
+        } else {
+            Self::deserialize(deserializer)
         }
-        Ok(result)
     }
 
     fn deserialize(deserializer: &mut DeserializerState) -> Result<Self, Error> {
         // ref flag
-        let ref_flag = deserializer.reader.i8();
+        let ref_flag = deserializer.reader.i8()?;
 
         if ref_flag == (RefFlag::NotNullValueFlag as i8)
             || ref_flag == (RefFlag::RefValueFlag as i8)
         {
-            // type_id
-            let type_id = deserializer.reader.i16();
-            let ty = if Self::is_vec() {
-                Self::vec_ty()
-            } else {
-                Self::ty()
-            };
-            if type_id != ty as i16 {
-                Err(Error::FieldType {
-                    expected: ty,
-                    actial: type_id,
-                })
-            } else {
-                Ok(Self::read(deserializer)?)
+            if ref_flag == (RefFlag::RefValueFlag as i8) {
+                // the writer tracks the object, it takes an id even if it can't be shared here
+                deserializer.reserve_ref();
             }
+            let type_id = deserializer.reader.i16()?;
+            Self::read_with_type(deserializer, type_id)
         } else if ref_flag == (RefFlag::NullFlag as i8) {
             Err(Error::Null)
         } else if ref_flag == (RefFlag::RefFlag as i8) {
@@ -78,11 +151,41 @@ where
     }
 }
 
+/// Read the type id and check that it is the one T is written with.
+fn read_type_id<T: FuryMeta>(deserializer: &mut DeserializerState) -> Result<(), Error> {
+    let type_id = deserializer.reader.i16()?;
+    check_type_id::<T>(type_id)
+}
+
+fn check_type_id<T: FuryMeta>(type_id: i16) -> Result<(), Error> {
+    let ty = T::field_ty();
+    if type_id != ty as i16 {
+        Err(Error::FieldType {
+            expected: ty,
+            actial: type_id,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Read a list whose elements have their own heads.
+fn read_list<T: Deserialize>(deserializer: &mut DeserializerState) -> Result<Vec<T>, Error> {
+    // length
+    let len = deserializer.reader.var_uint32()?;
+    // value
+    let mut result = Vec::new();
+    for _ in 0..len {
+        result.push(T::deserialize(deserializer)?);
+    }
+    Ok(result)
+}
+
 macro_rules! impl_num_deserialize {
     ($name: ident, $ty:tt) => {
         impl Deserialize for $ty {
             fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
-                Ok(deserializer.reader.$name())
+                deserializer.reader.$name()
             }
         }
     };
@@ -92,15 +195,11 @@ macro_rules! impl_num_deserialize_and_pritimive_vec {
     ($name: ident, $ty:tt) => {
         impl Deserialize for $ty {
             fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
-                Ok(deserializer.reader.$name())
+                deserializer.reader.$name()
             }
 
             fn read_vec(deserializer: &mut DeserializerState) -> Result<Vec<Self>, Error> {
-                // length
-                let len = (deserializer.reader.var_int32() as usize) * mem::size_of::<$ty>();
-                Ok(from_u8_slice::<$ty>(
-                    deserializer.reader.bytes(len as usize),
-                ))
+                read_le_array(deserializer, $ty::from_le_bytes)
             }
         }
     };
@@ -111,58 +210,203 @@ impl_num_deserialize!(u32, u32);
 impl_num_deserialize!(u64, u64);
 impl_num_deserialize!(i8, i8);
 
+impl Deserialize for usize {
+    fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+        let value = deserializer.reader.u64()?;
+        usize::try_from(value).map_err(|_| Error::Usize(value))
+    }
+}
+
 impl_num_deserialize_and_pritimive_vec!(u8, u8);
 impl_num_deserialize_and_pritimive_vec!(i16, i16);
-impl_num_deserialize_and_pritimive_vec!(i32, i32);
-impl_num_deserialize_and_pritimive_vec!(i64, i64);
 impl_num_deserialize_and_pritimive_vec!(f32, f32);
 impl_num_deserialize_and_pritimive_vec!(f64, f64);
 
-impl Deserialize for String {
+impl Deserialize for i32 {
     fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
-        let len = deserializer.reader.var_int32();
-        Ok(deserializer.reader.string(len as u32))
+        if deserializer.config.compress_int {
+            deserializer.reader.var_int32()
+        } else {
+            deserializer.reader.i32()
+        }
+    }
+
+    fn read_vec(deserializer: &mut DeserializerState) -> Result<Vec<Self>, Error> {
+        read_le_array(deserializer, i32::from_le_bytes)
     }
 }
 
-impl Deserialize for bool {
+impl Deserialize for i64 {
     fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
-        Ok(deserializer.reader.u8() == 1)
+        if !deserializer.config.compress_long {
+            deserializer.reader.i64()
+        } else if deserializer.config.long_encoding == LongEncoding::SLI {
+            deserializer.reader.sli_int64()
+        } else {
+            deserializer.reader.var_int64()
+        }
+    }
+
+    fn read_vec(deserializer: &mut DeserializerState) -> Result<Vec<Self>, Error> {
+        read_le_array(deserializer, i64::from_le_bytes)
     }
 }
 
-impl<T1: Deserialize + Eq + std::hash::Hash, T2: Deserialize> Deserialize for HashMap<T1, T2> {
+impl Deserialize for String {
     fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
-        // length
-        let len = deserializer.reader.var_int32();
-        let mut result = HashMap::new();
-        // key-value
+        if !deserializer.config.compress_string {
+            let len = deserializer.reader.var_uint32()?;
+            return deserializer.reader.string(len as usize);
+        }
+        let flag = deserializer.reader.u8()?;
+        let len = deserializer.reader.var_uint32()? as usize;
+        if flag == StringFlag::LATIN1 as u8 {
+            Ok(deserializer
+                .reader
+                .bytes(len)?
+                .iter()
+                .map(|b| *b as char)
+                .collect())
+        } else if flag == StringFlag::UTF16 as u8 {
+            let bytes = deserializer.reader.bytes(len)?;
+            if len % 2 != 0 {
+                return Err(Error::Utf16);
+            }
+            char::decode_utf16(
+                bytes
+                    .chunks_exact(2)
+                    .map(|c| u16::from_le_bytes([c[0], c[1]])),
+            )
+            .collect::<Result<String, _>>()
+            .map_err(|_| Error::Utf16)
+        } else if flag == StringFlag::UTF8 as u8 {
+            deserializer.reader.string(len)
+        } else {
+            Err(Error::StringFlag(flag))
+        }
+    }
+
+    fn read_vec(deserializer: &mut DeserializerState) -> Result<Vec<Self>, Error> {
+        // the strings of a string array have no heads
+        let len = deserializer.reader.var_uint32()?;
+        let mut result = Vec::new();
         for _ in 0..len {
-            result.insert(
-                <T1 as Deserialize>::deserialize(deserializer)?,
-                <T2 as Deserialize>::deserialize(deserializer)?,
-            );
+            result.push(Self::read(deserializer)?);
         }
         Ok(result)
     }
 }
 
-impl<T: Deserialize + Eq + std::hash::Hash> Deserialize for HashSet<T> {
+impl Deserialize for bool {
     fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
-        // length
-        let len = deserializer.reader.var_int32();
-        let mut result = HashSet::new();
-        // key-value
-        for _ in 0..len {
-            result.insert(<T as Deserialize>::deserialize(deserializer)?);
-        }
-        Ok(result)
+        Ok(deserializer.reader.u8()? == 1)
+    }
+
+    fn read_vec(deserializer: &mut DeserializerState) -> Result<Vec<Self>, Error> {
+        Ok(deserializer
+            .read_buffer()?
+            .iter()
+            .map(|b| *b == 1)
+            .collect())
+    }
+}
+
+/// Read a map into any collection of its entries, they are collected in the order they are written.
+fn read_map<T1: Deserialize, T2: Deserialize, C: FromIterator<(T1, T2)>>(
+    deserializer: &mut DeserializerState,
+) -> Result<C, Error> {
+    // length
+    let len = deserializer.reader.var_uint32()?;
+    // key-value
+    (0..len)
+        .map(|_| {
+            Ok((
+                <T1 as Deserialize>::deserialize(deserializer)?,
+                <T2 as Deserialize>::deserialize(deserializer)?,
+            ))
+        })
+        .collect()
+}
+
+impl<T1: Deserialize + Eq + Hash, T2: Deserialize, S: BuildHasher + Default> Deserialize
+    for HashMap<T1, T2, S>
+{
+    fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+        read_map(deserializer)
+    }
+}
+
+/// Maps of other languages like `TreeMap` of Java are read sorted by key.
+impl<T1: Deserialize + Ord, T2: Deserialize> Deserialize for BTreeMap<T1, T2> {
+    fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+        read_map(deserializer)
     }
 }
 
+/// IndexMap keeps the order the entries are written in, so a `LinkedHashMap` or a `TreeMap` of Java keeps its order.
+#[cfg(feature = "indexmap")]
+impl<T1: Deserialize + Eq + Hash, T2: Deserialize, S: BuildHasher + Default> Deserialize
+    for IndexMap<T1, T2, S>
+{
+    fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+        read_map(deserializer)
+    }
+}
+
+/// Read a set into any collection of its elements.
+fn read_set<T: Deserialize, C: FromIterator<T>>(
+    deserializer: &mut DeserializerState,
+) -> Result<C, Error> {
+    // length
+    let len = deserializer.reader.var_uint32()?;
+    (0..len)
+        .map(|_| <T as Deserialize>::deserialize(deserializer))
+        .collect()
+}
+
+impl<T: Deserialize + Eq + Hash, S: BuildHasher + Default> Deserialize for HashSet<T, S> {
+    fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+        read_set(deserializer)
+    }
+}
+
+impl<T: Deserialize + Ord> Deserialize for BTreeSet<T> {
+    fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+        read_set(deserializer)
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<T: Deserialize + Eq + Hash, S: BuildHasher + Default> Deserialize for IndexSet<T, S> {
+    fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+        read_set(deserializer)
+    }
+}
+
+/// Implement Deserialize for the lists other than Vec.
+///
+/// They are written as a list of headed elements, but they can be read from the primitive arrays a Vec is written as.
+macro_rules! impl_list_deserialize {
+    ($list: ident $(, $bound: path)?) => {
+        impl<T: Deserialize $(+ $bound)?> Deserialize for $list<T> {
+            fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+                Ok(read_list::<T>(deserializer)?.into_iter().collect())
+            }
+
+            fn read_with_type(deserializer: &mut DeserializerState, type_id: i16) -> Result<Self, Error> {
+                Ok(Vec::<T>::read_with_type(deserializer, type_id)?.into_iter().collect())
+            }
+        }
+    };
+}
+
+impl_list_deserialize!(VecDeque);
+impl_list_deserialize!(LinkedList);
+impl_list_deserialize!(BinaryHeap, Ord);
+
 impl Deserialize for NaiveDateTime {
     fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
-        let timestamp = deserializer.reader.u64();
+        let timestamp = deserializer.reader.u64()?;
         let ret = NaiveDateTime::from_timestamp_millis(timestamp as i64);
         match ret {
             Some(r) => Ok(r),
@@ -171,10 +415,58 @@ impl Deserialize for NaiveDateTime {
     }
 }
 
+/// A Vec is written as the array type of its elements, it can be read from a list of other languages as well,
+/// like `List[int16]` of pyfury which is an `ARRAY` of headed elements.
 impl<T: Deserialize> Deserialize for Vec<T> {
     fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
         T::read_vec(deserializer)
     }
+
+    fn read_with_type(deserializer: &mut DeserializerState, type_id: i16) -> Result<Self, Error> {
+        if type_id == Self::field_ty() as i16 {
+            Self::read(deserializer)
+        } else if type_id == FieldType::ARRAY as i16 {
+            read_list(deserializer)
+        } else {
+            Err(Error::FieldType {
+                expected: Self::field_ty(),
+                actial: type_id,
+            })
+        }
+    }
+}
+
+/// A fixed-size array is read like a Vec, which must have as many elements as the array.
+impl<T: Deserialize, const N: usize> Deserialize for [T; N] {
+    fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+        to_array(T::read_vec(deserializer)?)
+    }
+
+    fn read_with_type(deserializer: &mut DeserializerState, type_id: i16) -> Result<Self, Error> {
+        to_array(Vec::<T>::read_with_type(deserializer, type_id)?)
+    }
+}
+
+fn to_array<T, const N: usize>(value: Vec<T>) -> Result<[T; N], Error> {
+    value
+        .try_into()
+        .map_err(|value: Vec<T>| Error::ArrayLength {
+            expected: N,
+            actual: value.len(),
+        })
+}
+
+#[cfg(feature = "bytes")]
+impl Deserialize for bytes::Bytes {
+    fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+        match deserializer.out_of_band_buffer()? {
+            Some(buffer) => Ok(bytes::Bytes::copy_from_slice(buffer)),
+            None => {
+                let len = deserializer.reader.var_uint32()?;
+                deserializer.reader.shared_bytes(len as usize)
+            }
+        }
+    }
 }
 
 impl<T: Deserialize> Deserialize for Option<T> {
@@ -182,32 +474,205 @@ impl<T: Deserialize> Deserialize for Option<T> {
         Ok(Some(T::read(deserializer)?))
     }
 
+    fn null() -> Result<Self, Error> {
+        Ok(None)
+    }
+
     fn deserialize(deserializer: &mut DeserializerState) -> Result<Self, Error> {
-        // ref flag
-        let ref_flag = deserializer.reader.i8();
+        // only null is handled here, the other ref flags are left to T
+        if deserializer.reader.peek_i8()? == (RefFlag::NullFlag as i8) {
+            deserializer.reader.skip(1)?;
+            Ok(None)
+        } else {
+            Ok(Some(T::deserialize(deserializer)?))
+        }
+    }
 
-        if ref_flag == (RefFlag::NotNullValueFlag as i8)
-            || ref_flag == (RefFlag::RefValueFlag as i8)
-        {
-            // type_id
-            let type_id = deserializer.reader.i16();
-
-            if type_id != <Self as FuryMeta>::ty() as i16 {
-                Err(Error::FieldType {
-                    expected: <Self as FuryMeta>::ty(),
-                    actial: type_id,
-                })
-            } else {
-                Ok(Self::read(deserializer)?)
+    fn deserialize_field(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+        if deserializer.native {
+            read_option(deserializer, T::deserialize_field)
+        } else {
+            Self::deserialize(deserializer)
+        }
+    }
+}
+
+/// Read an Option of the native mode, it always has its own flag.
+fn read_option<T>(
+    deserializer: &mut DeserializerState,
+    read: fn(&mut DeserializerState) -> Result<T, Error>,
+) -> Result<Option<T>, Error> {
+    let flag = deserializer.reader.i8()?;
+    if flag == (RefFlag::NullFlag as i8) {
+        Ok(None)
+    } else if flag == (RefFlag::NotNullValueFlag as i8) {
+        Ok(Some(read(deserializer)?))
+    } else {
+        Err(Error::BadRefFlag)
+    }
+}
+
+impl<T: Deserialize, E: Deserialize> Deserialize for Result<T, E> {
+    fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+        match deserializer.reader.u8()? {
+            0 => Ok(Ok(T::deserialize_field(deserializer)?)),
+            1 => Ok(Err(E::deserialize_field(deserializer)?)),
+            flag => Err(Error::ResultFlag(flag)),
+        }
+    }
+}
+
+/// A tuple is read from a list, which must have as many elements as the tuple.
+macro_rules! impl_tuple_deserialize {
+    ($len: expr, $($name: ident $idx: tt),+) => {
+        impl<$($name: Deserialize),+> Deserialize for ($($name,)+) {
+            fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+                let len = deserializer.reader.var_uint32()? as usize;
+                if len != $len {
+                    return Err(Error::TupleLength {
+                        expected: $len,
+                        actual: len,
+                    });
+                }
+                Ok(($($name::deserialize(deserializer)?,)+))
             }
-        } else if ref_flag == (RefFlag::NullFlag as i8) {
-            Ok(None)
-        } else if ref_flag == (RefFlag::RefFlag as i8) {
-            Err(Error::Ref)
+        }
+    };
+}
+
+impl_tuple_deserialize!(1, A 0);
+impl_tuple_deserialize!(2, A 0, B 1);
+impl_tuple_deserialize!(3, A 0, B 1, C 2);
+impl_tuple_deserialize!(4, A 0, B 1, C 2, D 3);
+impl_tuple_deserialize!(5, A 0, B 1, C 2, D 3, E 4);
+impl_tuple_deserialize!(6, A 0, B 1, C 2, D 3, E 4, F 5);
+impl_tuple_deserialize!(7, A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_tuple_deserialize!(8, A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+impl_tuple_deserialize!(9, A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+impl_tuple_deserialize!(10, A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+impl_tuple_deserialize!(11, A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+impl_tuple_deserialize!(12, A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
+
+/// Rc and Arc are tracked by the ref table, an object which is referenced again comes back as the same pointer.
+macro_rules! impl_shared_deserialize {
+    ($ptr: ident, $read_shared: path) => {
+        impl<T: Deserialize + 'static> Deserialize for $ptr<T> {
+            fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+                Ok($ptr::new(T::read(deserializer)?))
+            }
+
+            fn deserialize(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+                // ref flag
+                let ref_flag = deserializer.reader.i8()?;
+
+                if ref_flag == (RefFlag::RefFlag as i8) {
+                    let id = deserializer.reader.var_uint32()?;
+                    deserializer.get_ref::<Self>(id)
+                } else if ref_flag == (RefFlag::RefValueFlag as i8) {
+                    // the id is taken before the nested objects take theirs
+                    let id = deserializer.reserve_ref();
+                    T::read_type_id(deserializer)?;
+                    $read_shared(deserializer, id)
+                } else if ref_flag == (RefFlag::NotNullValueFlag as i8) {
+                    T::read_type_id(deserializer)?;
+                    Self::read(deserializer)
+                } else if ref_flag == (RefFlag::NullFlag as i8) {
+                    Self::null()
+                } else {
+                    Err(Error::BadRefFlag)
+                }
+            }
+
+            fn null() -> Result<Self, Error> {
+                T::null().map($ptr::new)
+            }
+
+            fn deserialize_field(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+                Self::deserialize(deserializer)
+            }
+        }
+    };
+}
+
+fn read_arc<T: Deserialize + 'static>(
+    deserializer: &mut DeserializerState,
+    id: u32,
+) -> Result<Arc<T>, Error> {
+    let value = Arc::new(T::read(deserializer)?);
+    deserializer.set_ref(id, value.clone());
+    Ok(value)
+}
+
+impl_shared_deserialize!(Rc, T::read_rc);
+impl_shared_deserialize!(Arc, read_arc::<T>);
+
+/// RefCell makes `Rc<RefCell<T>>` able to form cycles, the object is shared before it is read.
+///
+/// A nested object which refers back to it gets the Rc of `T::default()`, which is filled in once `T` is read.
+/// Derived structs take part in cycles by deriving `Default` too, the back references are usually `Weak`:
+/// ```
+/// use fury::Fury;
+/// use std::{cell::RefCell, rc::{Rc, Weak}};
+///
+/// #[derive(Fury, Default)]
+/// #[tag("example.node")]
+/// struct Node {
+///     value: i32,
+///     parent: Weak<RefCell<Node>>,
+///     children: Vec<Rc<RefCell<Node>>>,
+/// }
+///
+/// let root = Rc::new(RefCell::new(Node::default()));
+/// let child = Rc::new(RefCell::new(Node { value: 1, parent: Rc::downgrade(&root), ..Default::default() }));
+/// root.borrow_mut().children.push(child);
+///
+/// let obj: Rc<RefCell<Node>> = fury::from_buffer(&fury::to_buffer(&root)).unwrap();
+/// let parent = obj.borrow().children[0].borrow().parent.upgrade().unwrap();
+/// assert!(Rc::ptr_eq(&parent, &obj));
+/// ```
+impl<T: Deserialize + Default + 'static> Deserialize for RefCell<T> {
+    fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+        Ok(RefCell::new(T::read(deserializer)?))
+    }
+
+    fn null() -> Result<Self, Error> {
+        T::null().map(RefCell::new)
+    }
+
+    fn read_rc(deserializer: &mut DeserializerState, id: u32) -> Result<Rc<Self>, Error> {
+        let value = Rc::new(RefCell::new(T::default()));
+        deserializer.set_ref(id, value.clone());
+        let inner = T::read(deserializer)?;
+        *value.borrow_mut() = inner;
+        Ok(value)
+    }
+}
+
+/// The object of a Weak is kept alive by the ref table only while reading,
+/// so it must be owned by an Rc somewhere else in the record.
+///
+/// It must be written with a ref id, an object without one isn't put into the ref table.
+impl<T: Deserialize + 'static> Deserialize for Weak<T> {
+    fn read(_deserializer: &mut DeserializerState) -> Result<Self, Error> {
+        // the ref flag is read already, the object wouldn't be owned by anything
+        Err(Error::WeakUntracked)
+    }
+
+    fn deserialize(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+        let ref_flag = deserializer.reader.peek_i8()?;
+        if ref_flag == (RefFlag::NullFlag as i8) {
+            deserializer.reader.skip(1)?;
+            Ok(Weak::new())
+        } else if ref_flag == (RefFlag::NotNullValueFlag as i8) {
+            Err(Error::WeakUntracked)
         } else {
-            Err(Error::BadRefFlag)
+            Ok(Rc::downgrade(&Rc::<T>::deserialize(deserializer)?))
         }
     }
+
+    fn deserialize_field(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+        Self::deserialize(deserializer)
+    }
 }
 
 lazy_static::lazy_static!(
@@ -216,58 +681,323 @@ lazy_static::lazy_static!(
 
 impl Deserialize for NaiveDate {
     fn read(serializer: &mut DeserializerState) -> Result<Self, Error> {
-        let days = serializer.reader.u64();
+        let days = serializer.reader.u64()?;
         match EPOCH.checked_add_days(Days::new(days)) {
             Some(value) => Ok(value.date_naive()),
             None => Err(Error::NaiveDate),
         }
     }
 }
-pub struct DeserializerState<'de, 'bf: 'de> {
+pub struct DeserializerState<'bf> {
     pub reader: Reader<'bf>,
-    pub tags: Vec<&'de str>,
+    pub tags: Vec<String>,
+    // the objects read with RefValueFlag by ref id, only shared pointers are kept
+    pub refs: Vec<Option<Box<dyn Any>>>,
+    pub config: Config,
+    // the out-of-band buffers which are left, only for payloads written with a buffer callback
+    pub buffers: Option<std::vec::IntoIter<&'bf [u8]>>,
+    // whether the payload is written in the native mode, it is taken from the language of the head
+    pub native: bool,
 }
 
-impl<'de, 'bf: 'de> DeserializerState<'de, 'bf> {
-    fn new(reader: Reader<'bf>) -> DeserializerState<'de, 'bf> {
+impl<'bf> DeserializerState<'bf> {
+    fn new(reader: Reader<'bf>, config: Config) -> DeserializerState<'bf> {
         DeserializerState {
             reader,
             tags: Vec::new(),
+            refs: Vec::new(),
+            config,
+            buffers: None,
+            native: false,
         }
     }
 
-    fn head(&mut self) -> Result<(), Error> {
-        let _bitmap = self.reader.u8();
-        let language: Language = self.reader.u8().try_into()?;
-        if Language::XLANG != language {
-            return Err(Error::UnsupportLanguage { language });
+    /// The next out-of-band buffer if the bytes of a binary or a primitive array are out of band,
+    /// the length and the bytes follow in the payload otherwise.
+    pub fn out_of_band_buffer(&mut self) -> Result<Option<&'bf [u8]>, Error> {
+        match self.buffers.as_mut() {
+            Some(buffers) if self.reader.u8()? == 0 => {
+                buffers.next().map(Some).ok_or(Error::BufferMissing)
+            }
+            _ => Ok(None),
         }
-        self.reader.skip(8); // native offset and size
-        Ok(())
+    }
+
+    /// Read the bytes of a binary or a primitive array, from the payload or the out-of-band buffers.
+    pub fn read_buffer(&mut self) -> Result<&[u8], Error> {
+        if let Some(buffer) = self.out_of_band_buffer()? {
+            return Ok(buffer);
+        }
+        let len = self.reader.var_uint32()?;
+        self.reader.bytes(len as usize)
+    }
+
+    /// Take the id of an object which is read with RefValueFlag.
+    pub fn reserve_ref(&mut self) -> u32 {
+        self.refs.push(None);
+        (self.refs.len() - 1) as u32
+    }
+
+    pub fn set_ref<T: Any>(&mut self, id: u32, value: T) {
+        self.refs[id as usize] = Some(Box::new(value));
+    }
+
+    /// The object of a RefFlag, it must be a finished object of the same type.
+    pub fn get_ref<T: Any + Clone>(&self, id: u32) -> Result<T, Error> {
+        self.refs
+            .get(id as usize)
+            .and_then(|value| value.as_ref()?.downcast_ref::<T>())
+            .cloned()
+            .ok_or(Error::RefId(id))
+    }
+
+    /// Skip a value with its heads, like a field of a compatible struct which the reader doesn't have.
+    ///
+    /// Tags and ref ids are still taken, so the values after it refer to the right ones.
+    pub fn skip_value(&mut self) -> Result<(), Error> {
+        let ref_flag = self.reader.i8()?;
+        if ref_flag == (RefFlag::NullFlag as i8) {
+            return Ok(());
+        } else if ref_flag == (RefFlag::RefFlag as i8) {
+            self.reader.var_uint32()?;
+            return Ok(());
+        } else if ref_flag == (RefFlag::RefValueFlag as i8) {
+            self.reserve_ref();
+        } else if ref_flag != (RefFlag::NotNullValueFlag as i8) {
+            return Err(Error::BadRefFlag);
+        }
+        let ty = self.reader.i16()?.try_into()?;
+        self.skip_body(ty)
+    }
+
+    fn skip_body(&mut self, ty: FieldType) -> Result<(), Error> {
+        let len = match ty {
+            FieldType::BOOL | FieldType::UINT8 | FieldType::INT8 => 1,
+            FieldType::UINT16 | FieldType::INT16 => 2,
+            FieldType::UINT32 | FieldType::FLOAT => 4,
+            FieldType::UINT64 | FieldType::DOUBLE | FieldType::DATE | FieldType::TIMESTAMP => 8,
+            // the width depends on the config
+            FieldType::INT32 => return i32::read(self).map(|_| ()),
+            FieldType::INT64 => return i64::read(self).map(|_| ()),
+            FieldType::STRING => return String::read(self).map(|_| ()),
+            FieldType::BINARY
+            | FieldType::FuryPrimitiveBoolArray
+            | FieldType::FuryPrimitiveShortArray
+            | FieldType::FuryPrimitiveIntArray
+            | FieldType::FuryPrimitiveLongArray
+            | FieldType::FuryPrimitiveFloatArray
+            | FieldType::FuryPrimitiveDoubleArray => return self.read_buffer().map(|_| ()),
+            FieldType::FuryStringArray => {
+                for _ in 0..self.reader.var_uint32()? {
+                    String::read(self)?;
+                }
+                return Ok(());
+            }
+            FieldType::ARRAY | FieldType::FurySet => {
+                for _ in 0..self.reader.var_uint32()? {
+                    self.skip_value()?;
+                }
+                return Ok(());
+            }
+            FieldType::MAP => {
+                for _ in 0..self.reader.var_uint32()? {
+                    self.skip_value()?;
+                    self.skip_value()?;
+                }
+                return Ok(());
+            }
+            // the value is written as a field, it has no head in the native mode
+            FieldType::RustResult if !self.native => {
+                self.reader.u8()?;
+                return self.skip_value();
+            }
+            // the variant may be written by its ordinal or by its name
+            FieldType::RustResult | FieldType::FuryEnum => return Err(Error::Skip(ty)),
+            FieldType::FuryTypeTag => {
+                self.read_tag()?;
+                // only the fields of the compatible layout carry their own names and types
+                if self.reader.u32()? != 0 {
+                    return Err(Error::Skip(ty));
+                }
+                for _ in 0..self.reader.var_uint32()? {
+                    let len = self.reader.var_uint32()?;
+                    self.reader.skip(len as usize)?;
+                    self.skip_value()?;
+                }
+                return Ok(());
+            }
+        };
+        self.reader.skip(len)
+    }
+
+    /// Read the head and the record.
+    fn read_record<T: Deserialize>(&mut self) -> Result<T, Error> {
+        if self.head()? {
+            T::null()
+        } else {
+            T::deserialize_field(self)
+        }
+    }
+
+    /// Read and check the head, whether the root value is null is returned.
+    fn head(&mut self) -> Result<bool, Error> {
+        let bitmap = self.reader.u8()?;
+        let version = (bitmap & config_flags::VERSION_MASK) >> config_flags::VERSION_SHIFT;
+        if version > config_flags::HEADER_VERSION {
+            return Err(Error::HeaderVersion(version));
+        }
+        if bitmap & config_flags::IS_NULL_FLAG != 0 {
+            return Ok(true);
+        }
+        if bitmap & config_flags::IS_LITTLE_ENDIAN_FLAG == 0 {
+            return Err(Error::BigEndian);
+        }
+        if bitmap & config_flags::IS_CROSS_LANGUAGE_FLAG == 0 {
+            return Err(Error::NotCrossLanguage);
+        }
+        if (bitmap & config_flags::IS_OUT_OF_BAND_FLAG != 0) != self.buffers.is_some() {
+            return Err(Error::OutOfBand);
+        }
+        // any writer, the layout of the cross-language payloads is the same
+        let language = Language::try_from(self.reader.u8()?)?;
+        self.native = language == Language::RUST;
+        self.reader.skip(8)?; // native offset and size
+        Ok(false)
+    }
+
+    /// The tag of the struct which is read next, it is left in the payload for the struct to read.
+    ///
+    /// The hash of a new tag is only checked once the struct reads it.
+    pub fn peek_tag(&mut self) -> Result<&str, Error> {
+        let tag_type = self.reader.peek_bytes(1)?[0];
+        if tag_type == USESTRINGID {
+            let bytes = self.reader.peek_bytes(1 + 2)?;
+            let id = i16::from_le_bytes([bytes[1], bytes[2]]);
+            self.tags
+                .get(id as usize)
+                .map(String::as_str)
+                .ok_or(Error::TagId(id))
+        } else if tag_type == USESTRINGVALUE {
+            // flag, hash and length
+            let head = 1 + 8 + 2;
+            let bytes = self.reader.peek_bytes(head)?;
+            let len = i16::from_le_bytes([bytes[head - 2], bytes[head - 1]]);
+            let end = usize::try_from(len)
+                .ok()
+                .and_then(|len| head.checked_add(len))
+                .ok_or(Error::TagLength(len))?;
+            let bytes = self.reader.peek_bytes(end)?;
+            Ok(std::str::from_utf8(&bytes[head..])?)
+        } else {
+            Err(Error::TagType(tag_type))
+        }
+    }
+
+    /// Read a trait object, the struct is picked from the registry of the config by its tag.
+    pub fn read_polymorphic<D: ?Sized + 'static>(&mut self) -> Result<Box<D>, Error> {
+        let registry = self.config.registry.clone();
+        let read = {
+            let tag = self.peek_tag()?;
+            registry
+                .reader::<D>(tag)
+                .ok_or_else(|| Error::UnregisteredTag(tag.to_string()))?
+        };
+        read(self)
     }
 
     pub fn read_tag(&mut self) -> Result<&str, Error> {
-        const USESTRINGVALUE: u8 = 0;
-        const USESTRINGID: u8 = 1;
-        let tag_type = self.reader.u8();
+        let tag_type = self.reader.u8()?;
         if tag_type == USESTRINGID {
-            Ok(self.tags[self.reader.i16() as usize])
+            let id = self.reader.i16()?;
+            self.tags
+                .get(id as usize)
+                .map(String::as_str)
+                .ok_or(Error::TagId(id))
         } else if tag_type == USESTRINGVALUE {
-            self.reader.skip(8); // todo tag hash
-            let len = self.reader.i16();
-            let tag: &str =
-                unsafe { std::str::from_utf8_unchecked(self.reader.bytes(len as usize)) };
+            let hash = self.reader.i64()?;
+            let len = self.reader.i16()?;
+            let len = usize::try_from(len).map_err(|_| Error::TagLength(len))?;
+            let tag = std::str::from_utf8(self.reader.bytes(len)?)?.to_string();
+            let expected = compute_tag_hash(&tag);
+            if hash != expected {
+                return Err(Error::TagHash {
+                    expected,
+                    actual: hash,
+                });
+            }
             self.tags.push(tag);
-            Ok(tag)
+            Ok(self.tags.last().unwrap())
         } else {
             Err(Error::TagType(tag_type))
         }
     }
 }
 
+const USESTRINGVALUE: u8 = 0;
+const USESTRINGID: u8 = 1;
+
 pub fn from_buffer<T: Deserialize>(bf: &[u8]) -> Result<T, Error> {
+    from_buffer_with_config(bf, &Config::default())
+}
+
+pub fn from_buffer_with_config<T: Deserialize>(bf: &[u8], config: &Config) -> Result<T, Error> {
+    let reader = Reader::new(bf);
+    let mut deserializer = DeserializerState::new(reader, config.clone());
+    deserializer.read_record()
+}
+
+/// Deserialize a record written with a buffer callback, the same as `buffers` of pyfury.
+///
+/// `buffers` are the ones the callback left out of the payload, in the order it was given them.
+pub fn from_buffer_with_buffers<'bf, T: Deserialize>(
+    bf: &'bf [u8],
+    buffers: &[&'bf [u8]],
+) -> Result<T, Error> {
+    from_buffer_with_buffers_and_config(bf, buffers, &Config::default())
+}
+
+pub fn from_buffer_with_buffers_and_config<'bf, T: Deserialize>(
+    bf: &'bf [u8],
+    buffers: &[&'bf [u8]],
+    config: &Config,
+) -> Result<T, Error> {
     let reader = Reader::new(bf);
-    let mut deserializer = DeserializerState::new(reader);
-    deserializer.head()?;
-    <T as Deserialize>::deserialize(&mut deserializer)
+    let mut deserializer = DeserializerState::new(reader, config.clone());
+    deserializer.buffers = Some(Vec::from(buffers).into_iter());
+    deserializer.read_record()
+}
+
+/// Deserialize one record from a source, reading it on demand instead of buffering the whole payload first.
+///
+/// Exactly the bytes of the record are taken from the source, so framed records can be read one after another.
+/// A source which ends in the middle of the record gives `Error::UnexpectedEof`.
+pub fn from_reader<T: Deserialize, R: io::BufRead>(source: R) -> Result<T, Error> {
+    from_reader_with_config(source, &Config::default())
+}
+
+pub fn from_reader_with_config<T: Deserialize, R: io::BufRead>(
+    mut source: R,
+    config: &Config,
+) -> Result<T, Error> {
+    let reader = Reader::with_source(&mut source);
+    let mut deserializer = DeserializerState::new(reader, config.clone());
+    deserializer.read_record()
+}
+
+/// Deserialize a record from `Bytes`.
+///
+/// `Bytes` fields share the allocation of `bf` instead of being copied.
+#[cfg(feature = "bytes")]
+pub fn from_bytes<T: Deserialize>(bf: &bytes::Bytes) -> Result<T, Error> {
+    from_bytes_with_config(bf, &Config::default())
+}
+
+#[cfg(feature = "bytes")]
+pub fn from_bytes_with_config<T: Deserialize>(
+    bf: &bytes::Bytes,
+    config: &Config,
+) -> Result<T, Error> {
+    let reader = Reader::from_bytes(bf);
+    let mut deserializer = DeserializerState::new(reader, config.clone());
+    deserializer.read_record()
 }
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::types::{FieldType, Language};
+use super::types::FieldType;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -31,6 +31,21 @@ pub enum Error {
     #[error("Bad FieldType; expected: {expected:?}, actual: {actial:?}")]
     FieldType { expected: FieldType, actial: i16 },
 
+    #[error("Unknown FieldType id: {0}")]
+    FieldTypeId(i16),
+
+    #[error("Can't skip a value of {0:?}, only structs in compatible mode can be skipped")]
+    Skip(FieldType),
+
+    #[error("Missing field: {0}")]
+    MissingField(&'static str),
+
+    #[error("Unknown enum variant: {0}")]
+    UnknownVariant(String),
+
+    #[error("Struct {0} is in the consistent layout, it can only be read with its type")]
+    UntypedStruct(String),
+
     #[error("Bad timestamp; out-of-range number of milliseconds")]
     NaiveDateTime,
 
@@ -43,9 +58,75 @@ pub enum Error {
     #[error("Bad Tag Type: {0}")]
     TagType(u8),
 
-    #[error("Only Xlang supported; receive: {language:?}")]
-    UnsupportLanguage { language: Language },
-
     #[error("Unsupported Language Code; receive: {code:?}")]
     UnsupportLanguageCode { code: u8 },
+
+    #[error("Only little-endian payloads are supported")]
+    BigEndian,
+
+    #[error("Only cross-language payloads are supported")]
+    NotCrossLanguage,
+
+    #[error("Unsupported header version: {0}; the payload is written by a newer fury")]
+    HeaderVersion(u8),
+
+    #[error("Unexpected end of buffer; needed: {needed}, remaining: {remaining}")]
+    UnexpectedEof { needed: usize, remaining: usize },
+
+    #[error("Bad Tag Hash; expected: {expected}, actual: {actual}")]
+    TagHash { expected: i64, actual: i64 },
+
+    #[error("Bad Tag Id: {0}")]
+    TagId(i16),
+
+    #[error("Bad Tag Length: {0}")]
+    TagLength(i16),
+
+    #[error("No type is registered with the tag {0}")]
+    UnregisteredTag(String),
+
+    #[error("Bad Ref Id: {0}; no shared object of this type was read with it")]
+    RefId(u32),
+
+    #[error("Weak can only be read from an object written with a ref id")]
+    WeakUntracked,
+
+    #[error("Bad utf8 string: {0}")]
+    Utf8(#[from] std::str::Utf8Error),
+
+    #[error("Bad utf16 string")]
+    Utf16,
+
+    #[error("Bad String Flag: {0}")]
+    StringFlag(u8),
+
+    #[error("Bad Result Flag: {0}")]
+    ResultFlag(u8),
+
+    #[error("Value {0} doesn't fit in usize")]
+    Usize(u64),
+
+    #[error("Bad tuple length; expected: {expected}, actual: {actual}")]
+    TupleLength { expected: usize, actual: usize },
+
+    #[error("Bad array length; expected: {expected}, actual: {actual}")]
+    ArrayLength { expected: usize, actual: usize },
+
+    #[error("Bad primitive array; {len} bytes aren't a whole number of {size} byte elements")]
+    ArrayBytes { len: usize, size: usize },
+
+    #[error("Io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Can't set bytes at offset {offset}, the bytes before {flushed} are flushed")]
+    Flushed { offset: usize, flushed: usize },
+
+    #[error("Out-of-band buffers must be given for the payloads written with a buffer callback, and only for them")]
+    OutOfBand,
+
+    #[error("Not enough out-of-band buffers")]
+    BufferMissing,
+
+    #[error("Buffer is full; needed: {needed}, capacity: {capacity}")]
+    BufferFull { needed: usize, capacity: usize },
 }
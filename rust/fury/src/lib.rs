@@ -13,23 +13,48 @@
 // limitations under the License.
 
 mod buffer;
+mod config;
 mod deserializer;
 mod error;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+mod murmur3;
+mod registry;
 mod row;
 mod serializer;
 mod types;
+mod value;
 
-pub use deserializer::from_buffer;
+pub use buffer::{Reader, Writer};
+pub use config::{Config, LongEncoding};
+pub use deserializer::{
+    from_buffer, from_buffer_with_buffers, from_buffer_with_buffers_and_config,
+    from_buffer_with_config, from_reader, from_reader_with_config,
+};
+#[cfg(feature = "bytes")]
+pub use deserializer::{from_bytes, from_bytes_with_config};
 pub use error::Error;
 pub use fury_derive::*;
-pub use row::{from_row, to_row};
-pub use serializer::to_buffer;
+pub use registry::{Polymorphic, Registry};
+pub use row::{from_row, to_row, to_row_slice};
+pub use serializer::{
+    serialized_size, serialized_size_with_config, to_buffer, to_buffer_into,
+    to_buffer_with_callback, to_buffer_with_callback_and_config, to_buffer_with_config, to_slice,
+    to_slice_with_config, to_writer, to_writer_with_config, BufferCallback, BufferObject,
+    SerializerState,
+};
+#[cfg(feature = "bytes")]
+pub use serializer::{to_buf_mut, to_buf_mut_with_config};
+pub use value::Value;
 
 pub mod __derive {
     pub use crate::buffer::{Reader, Writer};
     pub use crate::deserializer::{Deserialize, DeserializerState};
     pub use crate::row::{Row, StructViewer, StructWriter};
-    pub use crate::serializer::{Serialize, SerializerState};
-    pub use crate::types::{compute_struct_hash, FieldType, FuryMeta, SIZE_OF_REF_AND_TYPE};
+    pub use crate::serializer::{newtype_slice, Serialize, SerializerState};
+    pub use crate::types::{
+        compute_enum_hash, compute_struct_hash, compute_tag_hash, FieldType, FuryMeta,
+        SIZE_OF_REF_AND_TYPE,
+    };
     pub use crate::Error;
 }
@@ -0,0 +1,90 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Read payloads from memory-mapped files without copying them into memory.
+//!
+//! ```no_run
+//! use fury::mmap::MappedFile;
+//!
+//! let file = unsafe { MappedFile::open("snapshot.bin") }.unwrap();
+//! let value: Vec<String> = file.deserialize().unwrap();
+//! let row = file.row::<Vec<i32>>();
+//! ```
+
+use crate::buffer::Reader;
+use crate::deserializer::{from_buffer_with_config, Deserialize};
+use crate::row::{from_row, Row};
+use crate::{Config, Error};
+use memmap2::Mmap;
+use std::{fs::File, ops::Deref, path::Path};
+
+/// An owned read-only mapping of a file.
+///
+/// Readers, records and row viewers borrow from the handle, so they can be kept for as long as the handle is.
+pub struct MappedFile {
+    map: Mmap,
+}
+
+impl MappedFile {
+    /// Maps the whole file at `path`.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while it is mapped, by this or another process.
+    /// Otherwise the bytes change under the readers, or reading them faults.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> Result<MappedFile, Error> {
+        Self::map(&File::open(path)?)
+    }
+
+    /// Maps the whole file, the file may be closed afterwards.
+    ///
+    /// # Safety
+    ///
+    /// The same as [`MappedFile::open`].
+    pub unsafe fn map(file: &File) -> Result<MappedFile, Error> {
+        Ok(MappedFile {
+            map: Mmap::map(file)?,
+        })
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.map
+    }
+
+    pub fn reader(&self) -> Reader<'_> {
+        Reader::new(&self.map)
+    }
+
+    /// Deserialize the record which the file holds.
+    pub fn deserialize<T: Deserialize>(&self) -> Result<T, Error> {
+        from_buffer_with_config(&self.map, &Config::default())
+    }
+
+    pub fn deserialize_with_config<T: Deserialize>(&self, config: &Config) -> Result<T, Error> {
+        from_buffer_with_config(&self.map, config)
+    }
+
+    /// View the row which the file holds, the viewer reads the mapped bytes on demand.
+    pub fn row<'a, T: Row<'a>>(&'a self) -> T::ReadResult {
+        from_row::<T>(&self.map)
+    }
+}
+
+impl Deref for MappedFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.map
+    }
+}
@@ -0,0 +1,283 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::deserializer::{Deserialize, DeserializerState};
+use crate::error::Error;
+use crate::serializer::{Serialize, SerializerState};
+
+type ReadFn<D> = dyn Fn(&mut DeserializerState) -> Result<Box<D>, Error> + Send + Sync;
+
+#[derive(Clone, Default)]
+struct Types {
+    // the readers of each trait object type by tag, a reader is a `Box<ReadFn<D>>`
+    readers: HashMap<TypeId, HashMap<String, Arc<dyn Any + Send + Sync>>>,
+}
+
+/// The types a trait object field may hold, by their tags, the same as `ClassResolver.register` in Java.
+///
+/// The reader registers the types in the config, a trait object of a trait with `Polymorphic`
+/// as supertrait writes itself.
+/// ```
+/// use fury::{from_buffer_with_config, impl_polymorphic, to_buffer_with_config, Config, Fury, Polymorphic};
+///
+/// trait Shape: Polymorphic {
+///     fn area(&self) -> f64;
+/// }
+/// impl_polymorphic!(Shape);
+///
+/// #[derive(Fury)]
+/// #[tag("example.square")]
+/// struct Square {
+///     side: f64,
+/// }
+///
+/// impl Shape for Square {
+///     fn area(&self) -> f64 {
+///         self.side * self.side
+///     }
+/// }
+///
+/// let mut config = Config::default();
+/// config.registry.register_as::<dyn Shape, Square>(|v| Box::new(v));
+///
+/// let shapes: Vec<Box<dyn Shape>> = vec![Box::new(Square { side: 2.0 })];
+/// let bin = to_buffer_with_config(&shapes, &config);
+/// let obj: Vec<Box<dyn Shape>> = from_buffer_with_config(&bin, &config).unwrap();
+/// assert_eq!(obj[0].area(), 4.0);
+/// ```
+#[derive(Clone, Default)]
+pub struct Registry {
+    // shared by the clones of the config, it is copied on the first registration after a clone.
+    // An empty registry has none, so the default config doesn't allocate
+    types: Option<Arc<Types>>,
+}
+
+impl Registry {
+    /// Register T for `Box<dyn Polymorphic>` fields.
+    ///
+    /// # Panics
+    ///
+    /// If T has no tag, the same as `register_as`.
+    pub fn register<T: Serialize + Deserialize + 'static>(&mut self) -> &mut Self {
+        self.register_as::<dyn Polymorphic, T>(|v| Box::new(v))
+    }
+
+    /// Register T for `Box<D>` fields, `upcast` turns it into the trait object.
+    ///
+    /// # Panics
+    ///
+    /// If T has no tag, only the types which are written with their tags can be told apart.
+    pub fn register_as<D: ?Sized + 'static, T: Deserialize + 'static>(
+        &mut self,
+        upcast: fn(T) -> Box<D>,
+    ) -> &mut Self {
+        let tag = T::tag();
+        assert!(!tag.is_empty(), "only types with a tag can be registered");
+        let read: Box<ReadFn<D>> = Box::new(move |deserializer| Ok(upcast(T::read(deserializer)?)));
+        self.types_mut()
+            .readers
+            .entry(TypeId::of::<D>())
+            .or_default()
+            .insert(tag.to_string(), Arc::new(read));
+        self
+    }
+
+    fn types_mut(&mut self) -> &mut Types {
+        Arc::make_mut(self.types.get_or_insert_with(Default::default))
+    }
+
+    pub(crate) fn reader<D: ?Sized + 'static>(&self, tag: &str) -> Option<&ReadFn<D>> {
+        self.types
+            .as_ref()?
+            .readers
+            .get(&TypeId::of::<D>())?
+            .get(tag)?
+            .downcast_ref::<Box<ReadFn<D>>>()
+            .map(Box::as_ref)
+    }
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tags = self
+            .types
+            .iter()
+            .flat_map(|types| types.readers.values())
+            .flat_map(HashMap::keys);
+        f.debug_set().entries(tags).finish()
+    }
+}
+
+/// Object-safe serialization, traits whose objects are fields take it as a supertrait.
+///
+/// It is implemented for every type, `impl_polymorphic!` then implements the serialization of the trait object.
+/// `Box<dyn Polymorphic>` itself is a field of any type, it takes the place of `Box<dyn Any>`,
+/// which can't be written as the type in it isn't known.
+pub trait Polymorphic: Any {
+    fn serialize_dyn(&self, serializer: &mut SerializerState);
+
+    fn serialized_size_dyn(&self, serializer: &mut SerializerState) -> usize;
+
+    fn write_dyn(&self, serializer: &mut SerializerState);
+
+    fn size_dyn(&self, serializer: &mut SerializerState) -> usize;
+
+    fn write_type_id_dyn(&self, serializer: &mut SerializerState);
+
+    fn type_id_size_dyn(&self) -> usize;
+
+    fn is_null_dyn(&self) -> bool;
+
+    /// The object as `dyn Any`, the trait objects of the subtraits can't be upcast otherwise.
+    ///
+    /// A `Box<dyn Trait>` is `Polymorphic` too, call the `downcast_ref` of the trait object instead.
+    fn as_any(&self) -> &dyn Any;
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<T: Serialize + Any> Polymorphic for T {
+    fn serialize_dyn(&self, serializer: &mut SerializerState) {
+        self.serialize(serializer);
+    }
+
+    fn serialized_size_dyn(&self, serializer: &mut SerializerState) -> usize {
+        self.serialized_size(serializer)
+    }
+
+    fn write_dyn(&self, serializer: &mut SerializerState) {
+        self.write(serializer);
+    }
+
+    fn size_dyn(&self, serializer: &mut SerializerState) -> usize {
+        self.size(serializer)
+    }
+
+    fn write_type_id_dyn(&self, serializer: &mut SerializerState) {
+        self.write_type_id(serializer);
+    }
+
+    fn type_id_size_dyn(&self) -> usize {
+        self.type_id_size()
+    }
+
+    fn is_null_dyn(&self) -> bool {
+        self.is_null()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+/// Implement the serialization of `Box<dyn Trait>`, the trait must have `Polymorphic` as supertrait.
+///
+/// The object is written as the struct it is, and read as the one the registry of the config has for its tag.
+/// The trait object gets `downcast_ref` and `downcast` to get the struct back.
+#[macro_export]
+macro_rules! impl_polymorphic {
+    ($trait: path) => {
+        impl dyn $trait {
+            pub fn downcast_ref<T: std::any::Any>(&self) -> Option<&T> {
+                $crate::Polymorphic::as_any(self).downcast_ref()
+            }
+
+            pub fn downcast<T: std::any::Any>(self: Box<Self>) -> Result<Box<T>, Box<Self>> {
+                if $crate::Polymorphic::as_any(self.as_ref()).is::<T>() {
+                    Ok($crate::Polymorphic::into_any(self)
+                        .downcast()
+                        .expect("should be the checked type"))
+                } else {
+                    Err(self)
+                }
+            }
+        }
+
+        /// The type is only known from the value, a field of it isn't part of the struct hash.
+        impl $crate::__derive::FuryMeta for Box<dyn $trait> {
+            fn ty() -> $crate::__derive::FieldType {
+                $crate::__derive::FieldType::FuryTypeTag
+            }
+        }
+
+        /// The object is written by its own type, `Rc<Box<dyn Trait>>` writes it through `write`.
+        impl $crate::__derive::Serialize for Box<dyn $trait> {
+            fn write(&self, serializer: &mut $crate::__derive::SerializerState) {
+                $crate::Polymorphic::write_dyn(self.as_ref(), serializer);
+            }
+
+            fn size(&self, serializer: &mut $crate::__derive::SerializerState) -> usize {
+                $crate::Polymorphic::size_dyn(self.as_ref(), serializer)
+            }
+
+            fn write_type_id(&self, serializer: &mut $crate::__derive::SerializerState) {
+                $crate::Polymorphic::write_type_id_dyn(self.as_ref(), serializer);
+            }
+
+            fn type_id_size(&self) -> usize {
+                $crate::Polymorphic::type_id_size_dyn(self.as_ref())
+            }
+
+            fn is_null(&self) -> bool {
+                $crate::Polymorphic::is_null_dyn(self.as_ref())
+            }
+
+            fn reserved_space() -> usize {
+                0
+            }
+
+            fn serialize(&self, serializer: &mut $crate::__derive::SerializerState) {
+                $crate::Polymorphic::serialize_dyn(self.as_ref(), serializer);
+            }
+
+            fn serialized_size(&self, serializer: &mut $crate::__derive::SerializerState) -> usize {
+                $crate::Polymorphic::serialized_size_dyn(self.as_ref(), serializer)
+            }
+
+            fn serialize_field(&self, serializer: &mut $crate::__derive::SerializerState) {
+                // the tag is needed in the native mode too
+                self.serialize(serializer);
+            }
+
+            fn field_size(&self, serializer: &mut $crate::__derive::SerializerState) -> usize {
+                self.serialized_size(serializer)
+            }
+        }
+
+        impl $crate::__derive::Deserialize for Box<dyn $trait> {
+            fn read(
+                deserializer: &mut $crate::__derive::DeserializerState,
+            ) -> Result<Self, $crate::Error> {
+                deserializer.read_polymorphic::<dyn $trait>()
+            }
+
+            fn deserialize_field(
+                deserializer: &mut $crate::__derive::DeserializerState,
+            ) -> Result<Self, $crate::Error> {
+                Self::deserialize(deserializer)
+            }
+        }
+    };
+}
+
+impl_polymorphic!(Polymorphic);
@@ -19,4 +19,4 @@ mod writer;
 
 pub use reader::{from_row, ArrayViewer, StructViewer};
 pub use row::Row;
-pub use writer::{to_row, ArrayWriter, StructWriter};
+pub use writer::{to_row, to_row_slice, ArrayWriter, StructWriter};
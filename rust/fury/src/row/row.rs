@@ -131,6 +131,22 @@ impl<'a> Row<'a> for Vec<u8> {
     }
 }
 
+/// A byte array is binary like Vec<u8>, reading it checks that the row holds as many bytes.
+impl<'a, const N: usize> Row<'a> for [u8; N] {
+    type ReadResult = Result<&'a [u8; N], Error>;
+
+    fn write(v: &Self, writer: &mut Writer) {
+        writer.bytes(v);
+    }
+
+    fn cast(bytes: &'a [u8]) -> Self::ReadResult {
+        bytes.try_into().map_err(|_| Error::ArrayLength {
+            expected: N,
+            actual: bytes.len(),
+        })
+    }
+}
+
 pub struct ArrayGetter<'a, T> {
     array_data: ArrayViewer<'a>,
     _marker: PhantomData<T>,
@@ -150,16 +166,20 @@ impl<'a, T: Row<'a>> ArrayGetter<'a, T> {
     }
 }
 
+fn write_array<'a, T: Row<'a>>(v: &[T], writer: &mut Writer) {
+    let mut array_writer = ArrayWriter::new(v.len(), writer);
+    v.iter().enumerate().for_each(|(idx, item)| {
+        let callback_info = array_writer.write_start(idx);
+        <T as Row>::write(item, array_writer.get_writer());
+        array_writer.write_end(callback_info);
+    });
+}
+
 impl<'a, T: Row<'a>> Row<'a> for Vec<T> {
     type ReadResult = ArrayGetter<'a, T>;
 
     fn write(v: &Self, writer: &mut Writer) {
-        let mut array_writer = ArrayWriter::new(v.len(), writer);
-        v.iter().enumerate().for_each(|(idx, item)| {
-            let callback_info = array_writer.write_start(idx);
-            <T as Row>::write(item, array_writer.get_writer());
-            array_writer.write_end(callback_info);
-        });
+        write_array(v, writer);
     }
 
     fn cast(row: &'a [u8]) -> Self::ReadResult {
@@ -170,6 +190,26 @@ impl<'a, T: Row<'a>> Row<'a> for Vec<T> {
     }
 }
 
+/// A fixed-size array is written like a Vec, reading it checks that the row holds as many elements.
+impl<'a, T: Row<'a>, const N: usize> Row<'a> for [T; N] {
+    type ReadResult = Result<ArrayGetter<'a, T>, Error>;
+
+    fn write(v: &Self, writer: &mut Writer) {
+        write_array(v, writer);
+    }
+
+    fn cast(row: &'a [u8]) -> Self::ReadResult {
+        let getter = <Vec<T> as Row>::cast(row);
+        if getter.size() != N {
+            return Err(Error::ArrayLength {
+                expected: N,
+                actual: getter.size(),
+            });
+        }
+        Ok(getter)
+    }
+}
+
 pub struct MapGetter<'a, T1, T2>
 where
     T1: Ord,
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use crate::buffer::Writer;
+use crate::error::Error;
 
 use super::{bit_util::calculate_bitmap_width_in_bytes, row::Row};
 
@@ -21,28 +22,29 @@ pub struct WriteCallbackInfo {
     data_start: usize,
 }
 
-struct FieldWriterHelper<'a> {
-    pub writer: &'a mut Writer,
+struct FieldWriterHelper<'a, 'w> {
+    pub writer: &'a mut Writer<'w>,
     base_offset: usize,
-    get_field_offset: Box<dyn Fn(usize) -> usize>,
+    // offset of the first field slot, each slot is 8 bytes
+    fields_offset: usize,
 }
 
-impl<'a> FieldWriterHelper<'a> {
+impl<'a, 'w> FieldWriterHelper<'a, 'w> {
     fn new(
-        writer: &'a mut Writer,
+        writer: &'a mut Writer<'w>,
         base_offset: usize,
-        get_field_offset: Box<dyn Fn(usize) -> usize>,
-    ) -> FieldWriterHelper {
+        fields_offset: usize,
+    ) -> FieldWriterHelper<'a, 'w> {
         FieldWriterHelper {
             writer,
             base_offset,
-            get_field_offset,
+            fields_offset,
         }
     }
 
     fn write_start(&mut self, idx: usize) -> WriteCallbackInfo {
         let base_offset = self.base_offset;
-        let field_offset = (self.get_field_offset)(idx);
+        let field_offset = self.fields_offset + idx * 8;
         let writer: &mut Writer = self.writer;
         let offset = writer.len() - base_offset;
         writer.set_bytes(field_offset, &(offset as u32).to_le_bytes());
@@ -60,15 +62,15 @@ impl<'a> FieldWriterHelper<'a> {
     }
 }
 
-pub struct StructWriter<'a> {
-    field_writer_helper: FieldWriterHelper<'a>,
+pub struct StructWriter<'a, 'w> {
+    field_writer_helper: FieldWriterHelper<'a, 'w>,
 }
 
-impl<'a> StructWriter<'a> {
+impl<'a, 'w> StructWriter<'a, 'w> {
     fn get_fixed_size(bit_map_width_in_bytes: usize, num_fields: usize) -> usize {
         bit_map_width_in_bytes + num_fields * 8
     }
-    pub fn new(num_fields: usize, writer: &mut Writer) -> StructWriter {
+    pub fn new(num_fields: usize, writer: &'a mut Writer<'w>) -> StructWriter<'a, 'w> {
         let base_offset = writer.len();
         let bit_map_width_in_bytes = calculate_bitmap_width_in_bytes(num_fields);
 
@@ -76,7 +78,7 @@ impl<'a> StructWriter<'a> {
             field_writer_helper: FieldWriterHelper::new(
                 writer,
                 base_offset,
-                Box::new(move |idx| base_offset + bit_map_width_in_bytes + idx * 8),
+                base_offset + bit_map_width_in_bytes,
             ),
         };
         let fixed_size = Self::get_fixed_size(bit_map_width_in_bytes, num_fields);
@@ -85,7 +87,7 @@ impl<'a> StructWriter<'a> {
         struct_writer
     }
 
-    pub fn get_writer(&mut self) -> &mut Writer {
+    pub fn get_writer(&mut self) -> &mut Writer<'w> {
         self.field_writer_helper.writer
     }
 
@@ -98,23 +100,23 @@ impl<'a> StructWriter<'a> {
     }
 }
 
-pub struct ArrayWriter<'a> {
-    field_writer_helper: FieldWriterHelper<'a>,
+pub struct ArrayWriter<'a, 'w> {
+    field_writer_helper: FieldWriterHelper<'a, 'w>,
 }
 
-impl<'a> ArrayWriter<'a> {
+impl<'a, 'w> ArrayWriter<'a, 'w> {
     fn get_fixed_size(bit_map_width_in_bytes: usize, num_fields: usize) -> usize {
         8 + bit_map_width_in_bytes + num_fields * 8
     }
 
-    pub fn new(num_fields: usize, writer: &mut Writer) -> ArrayWriter {
+    pub fn new(num_fields: usize, writer: &'a mut Writer<'w>) -> ArrayWriter<'a, 'w> {
         let base_offset = writer.len();
         let bit_map_width_in_bytes = calculate_bitmap_width_in_bytes(num_fields);
         let array_writer = ArrayWriter {
             field_writer_helper: FieldWriterHelper::new(
                 writer,
                 base_offset,
-                Box::new(move |idx| 8 + base_offset + bit_map_width_in_bytes + idx * 8),
+                8 + base_offset + bit_map_width_in_bytes,
             ),
         };
         let fixed_size = Self::get_fixed_size(bit_map_width_in_bytes, num_fields);
@@ -127,7 +129,7 @@ impl<'a> ArrayWriter<'a> {
         array_writer
     }
 
-    pub fn get_writer(&mut self) -> &mut Writer {
+    pub fn get_writer(&mut self) -> &mut Writer<'w> {
         self.field_writer_helper.writer
     }
 
@@ -140,18 +142,18 @@ impl<'a> ArrayWriter<'a> {
     }
 }
 
-pub struct MapWriter<'a> {
+pub struct MapWriter<'a, 'w> {
     base_offset: usize,
-    writer: &'a mut Writer,
+    writer: &'a mut Writer<'w>,
 }
 
-impl<'a> MapWriter<'a> {
+impl<'a, 'w> MapWriter<'a, 'w> {
     fn get_fixed_size(&self) -> usize {
         // key_byte_size
         8
     }
 
-    pub fn new(writer: &mut Writer) -> MapWriter {
+    pub fn new(writer: &'a mut Writer<'w>) -> MapWriter<'a, 'w> {
         let base_offset = writer.len();
         let array_writer = MapWriter {
             writer,
@@ -163,7 +165,7 @@ impl<'a> MapWriter<'a> {
         array_writer
     }
 
-    pub fn get_writer(&mut self) -> &mut Writer {
+    pub fn get_writer(&mut self) -> &mut Writer<'w> {
         self.writer
     }
 
@@ -181,5 +183,13 @@ impl<'a> MapWriter<'a> {
 pub fn to_row<'a, T: Row<'a>>(v: &T) -> Vec<u8> {
     let mut writer = Writer::default();
     T::write(v, &mut writer);
-    writer.dump()
+    writer.into_inner()
+}
+
+/// Write the row into the start of `bf` without allocating, the number of bytes written is returned.
+pub fn to_row_slice<'a, T: Row<'a>>(v: &T, bf: &mut [u8]) -> Result<usize, Error> {
+    let mut writer = Writer::from(bf);
+    T::write(v, &mut writer);
+    writer.flush()?;
+    Ok(writer.len())
 }
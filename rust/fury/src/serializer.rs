@@ -31,22 +31,70 @@
 //! fury_derive would expand the code and automatic implements the Serialize trait
 
 use super::buffer::Writer;
-use super::types::{config_flags, FuryMeta, Language, RefFlag, SIZE_OF_REF_AND_TYPE};
+use super::config::{Config, LongEncoding};
+use super::error::Error;
+use super::types::{
+    compute_tag_hash, config_flags, FuryMeta, Language, RefFlag, StringFlag, SIZE_OF_REF_AND_TYPE,
+};
 use chrono::{NaiveDate, NaiveDateTime};
-use std::collections::{HashMap, HashSet};
-use std::mem;
+#[cfg(feature = "indexmap")]
+use indexmap::{IndexMap, IndexSet};
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, LinkedList, VecDeque};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    io, mem,
+    rc::{Rc, Weak},
+    sync::Arc,
+};
 
 /// Convert a typed slice to a u8 slice.
 /// Usually used to convert a typed array like Vec to &[u8], which can be easily written to a buffer.
+/// The bytes are in native order, so multi-byte numbers must only be written this way on little-endian hosts.
 fn to_u8_slice<T>(slice: &[T]) -> &[u8] {
     let byte_len = std::mem::size_of_val(slice);
     unsafe { std::slice::from_raw_parts(slice.as_ptr().cast::<u8>(), byte_len) }
 }
 
+/// View a slice of newtypes as a slice of the type they wrap, so a Vec of newtypes is written like a Vec of that type.
+///
+/// # Safety
+/// `N` must be a struct whose only field is a `T`.
+pub unsafe fn newtype_slice<N, T>(slice: &[N]) -> &[T] {
+    // the only field of a struct of the same size and alignment sits at its start
+    assert!(
+        mem::size_of::<N>() == mem::size_of::<T>() && mem::align_of::<N>() == mem::align_of::<T>()
+    );
+    std::slice::from_raw_parts(slice.as_ptr().cast::<T>(), slice.len())
+}
+
+/// Write a primitive array, the elements are little-endian.
+/// On little-endian hosts it is the memory layout of the array, so it is written as it is.
+fn write_le_array<'se, T: Copy>(
+    value: &[T],
+    serializer: &mut SerializerState<'se>,
+    write: fn(&mut Writer<'se>, T),
+) {
+    if cfg!(target_endian = "little") {
+        serializer.write_buffer(to_u8_slice(value));
+    } else {
+        let mut bytes = Writer::default();
+        for item in value {
+            write(&mut bytes, *item);
+        }
+        serializer.write_buffer(bytes.as_slice());
+    }
+}
+
+/// The exact number of bytes written by write_le_array.
+fn le_array_size<T>(value: &[T], serializer: &SerializerState) -> usize {
+    serializer.buffer_size(mem::size_of_val(value))
+}
+
 /// Types that implement the Serialize trait can be serialized to Fury.
 ///
 /// 1. Normal situation:
-/// The order of function calls is reserved_space -> serialize -> write.
+///    The order of function calls is reserved_space -> serialize -> write.
 ///     a. reserved_space is used to allocate the fixed memory space, which can avoid the cost of the memory check.
 ///         However, dynamic types like strings should allocate the size separately before being written to the buffer.
 ///     b. serialize is used to serialize the data into the buffer. The first step is to write the object head,
@@ -54,10 +102,13 @@ fn to_u8_slice<T>(slice: &[T]) -> &[u8] {
 ///         The second step is to call the write function, which is used to write the Rust object.
 ///     c. write is used to write the Rust object into the buffer.
 /// 2. Vec situation:
-/// If the object is in a Vec, the call order is reserved_space -> serialize -> write -> write_vec.
-/// The write_vec function is used to write the elements of the Vec. But why can't we just loop through the elements and write each element one by one?
-/// This is because Fury includes some primitive types like FuryPrimitiveBoolArray which do not include the head of the elements,
-/// but other Vecs do. So the write_vec function is necessary to handle the differences. Primitive arrays can overwrite the function.
+///    If the object is in a Vec or a fixed-size array, the call order is reserved_space -> serialize -> write -> write_vec.
+///    The write_vec function is used to write the elements of the Vec. But why can't we just loop through the elements and write each element one by one?
+///    This is because Fury includes some primitive types like FuryPrimitiveBoolArray which do not include the head of the elements,
+///    but other Vecs do. So the write_vec function is necessary to handle the differences. Primitive arrays can overwrite the function.
+/// 3. Size situation:
+///    serialized_size, size and vec_size mirror serialize, write and write_vec, they return the exact number of bytes
+///    those functions write with the same state, without writing anything.
 pub trait Serialize
 where
     Self: Sized + FuryMeta,
@@ -68,8 +119,8 @@ where
     /// Step 1: write the length of the Vec into the buffer.
     /// Step 2: reserve the fixed size of all the elements.
     /// Step 3: loop through the Vec and invoke the serialize function of each item.
-    fn write_vec(value: &Vec<Self>, serializer: &mut SerializerState) {
-        serializer.writer.var_int32(value.len() as i32);
+    fn write_vec(value: &[Self], serializer: &mut SerializerState) {
+        serializer.writer.var_uint32(value.len() as u32);
         serializer
             .writer
             .reserve((<Self as Serialize>::reserved_space() + SIZE_OF_REF_AND_TYPE) * value.len());
@@ -85,6 +136,76 @@ where
     /// Write the data into the buffer.
     fn write(&self, serializer: &mut SerializerState);
 
+    /// The exact number of bytes written by the write function.
+    ///
+    /// The default writes the value into a scratch buffer and counts the bytes,
+    /// the types of this crate compute it instead to save that pass.
+    fn size(&self, serializer: &mut SerializerState) -> usize {
+        let writer = mem::take(&mut serializer.writer);
+        // the buffers are taken as in band, the same as buffer_size
+        let callback = serializer.buffer_callback.take();
+        if callback.is_some() {
+            serializer.buffer_callback = Some(Box::new(|_: BufferObject| true));
+        }
+        self.write(serializer);
+        let size = serializer.writer.len();
+        serializer.writer = writer;
+        serializer.buffer_callback = callback;
+        size
+    }
+
+    /// The exact number of bytes written by the write_vec function.
+    fn vec_size(value: &[Self], serializer: &mut SerializerState) -> usize {
+        Writer::var_uint32_size(value.len() as u32)
+            + value
+                .iter()
+                .map(|item| item.serialized_size(serializer))
+                .sum::<usize>()
+    }
+
+    /// Whether the value is null, only Option has one.
+    fn is_null(&self) -> bool {
+        false
+    }
+
+    /// Write the value as a field of a struct with the consistent layout.
+    ///
+    /// The native mode drops the ref flag and the type id, both sides know the schema already.
+    /// Types which need their ref flag to be read back overwrite it.
+    fn serialize_field(&self, serializer: &mut SerializerState) {
+        if serializer.config.native {
+            self.write(serializer);
+        } else {
+            self.serialize(serializer);
+        }
+    }
+
+    /// The exact number of bytes written by the serialize_field function.
+    fn field_size(&self, serializer: &mut SerializerState) -> usize {
+        if serializer.config.native {
+            self.size(serializer)
+        } else {
+            self.serialized_size(serializer)
+        }
+    }
+
+    /// The exact number of bytes written by the serialize function.
+    fn serialized_size(&self, serializer: &mut SerializerState) -> usize {
+        1 + self.type_id_size() + self.size(serializer)
+    }
+
+    /// Write the type id which follows the ref flag.
+    ///
+    /// Types which only know their type id from the value, like `Value`, write it in `write` and overwrite this.
+    fn write_type_id(&self, serializer: &mut SerializerState) {
+        serializer.writer.i16(Self::field_ty() as i16);
+    }
+
+    /// The exact number of bytes written by the write_type_id function.
+    fn type_id_size(&self) -> usize {
+        mem::size_of::<i16>()
+    }
+
     /// Entry point of the serialization.
     ///
     /// Step 1: write the type flag and type flag into the buffer.
@@ -93,11 +214,7 @@ where
         // ref flag
         serializer.writer.i8(RefFlag::NotNullValueFlag as i8);
         // type
-        serializer.writer.i16(if Self::is_vec() {
-            Self::vec_ty()
-        } else {
-            Self::ty()
-        } as i16);
+        self.write_type_id(serializer);
         self.write(serializer);
     }
 }
@@ -112,6 +229,10 @@ macro_rules! impl_num_serialize {
                 serializer.writer.$name(*self);
             }
 
+            fn size(&self, _serializer: &mut SerializerState) -> usize {
+                mem::size_of::<$ty>()
+            }
+
             fn reserved_space() -> usize {
                 mem::size_of::<$ty>()
             }
@@ -127,9 +248,16 @@ macro_rules! impl_num_serialize_and_pritimive_vec {
                 serializer.writer.$name(*self);
             }
 
-            fn write_vec(value: &Vec<Self>, serializer: &mut SerializerState) {
-                serializer.writer.var_int32(value.len() as i32);
-                serializer.writer.bytes(to_u8_slice(value.as_slice()));
+            fn write_vec(value: &[Self], serializer: &mut SerializerState) {
+                write_le_array(value, serializer, Writer::$name);
+            }
+
+            fn size(&self, _serializer: &mut SerializerState) -> usize {
+                mem::size_of::<$ty>()
+            }
+
+            fn vec_size(value: &[Self], serializer: &mut SerializerState) -> usize {
+                le_array_size(value, serializer)
             }
 
             fn reserved_space() -> usize {
@@ -144,22 +272,131 @@ impl_num_serialize!(u32, u32);
 impl_num_serialize!(u64, u64);
 impl_num_serialize!(i8, i8);
 
+/// usize is written as u64 whatever the width of the platform.
+impl Serialize for usize {
+    fn write(&self, serializer: &mut SerializerState) {
+        serializer.writer.u64(*self as u64);
+    }
+
+    fn size(&self, _serializer: &mut SerializerState) -> usize {
+        mem::size_of::<u64>()
+    }
+
+    fn reserved_space() -> usize {
+        mem::size_of::<u64>()
+    }
+}
+
 impl_num_serialize_and_pritimive_vec!(u8, u8);
 impl_num_serialize_and_pritimive_vec!(i16, i16);
-impl_num_serialize_and_pritimive_vec!(i32, i32);
-impl_num_serialize_and_pritimive_vec!(i64, i64);
 impl_num_serialize_and_pritimive_vec!(f32, f32);
 impl_num_serialize_and_pritimive_vec!(f64, f64);
 
+/// i32 and i64 are compressed when the config asks for it, but their primitive arrays are always fixed width.
+impl Serialize for i32 {
+    fn write(&self, serializer: &mut SerializerState) {
+        if serializer.config.compress_int {
+            serializer.writer.var_int32(*self);
+        } else {
+            serializer.writer.i32(*self);
+        }
+    }
+
+    fn write_vec(value: &[Self], serializer: &mut SerializerState) {
+        write_le_array(value, serializer, Writer::i32);
+    }
+
+    fn size(&self, serializer: &mut SerializerState) -> usize {
+        if serializer.config.compress_int {
+            Writer::var_int32_size(*self)
+        } else {
+            mem::size_of::<i32>()
+        }
+    }
+
+    fn vec_size(value: &[Self], serializer: &mut SerializerState) -> usize {
+        le_array_size(value, serializer)
+    }
+
+    fn reserved_space() -> usize {
+        mem::size_of::<i32>()
+    }
+}
+
+impl Serialize for i64 {
+    fn write(&self, serializer: &mut SerializerState) {
+        if !serializer.config.compress_long {
+            serializer.writer.i64(*self);
+        } else if serializer.config.long_encoding == LongEncoding::SLI {
+            serializer.writer.sli_int64(*self);
+        } else {
+            serializer.writer.var_int64(*self);
+        }
+    }
+
+    fn write_vec(value: &[Self], serializer: &mut SerializerState) {
+        write_le_array(value, serializer, Writer::i64);
+    }
+
+    fn size(&self, serializer: &mut SerializerState) -> usize {
+        if !serializer.config.compress_long {
+            mem::size_of::<i64>()
+        } else if serializer.config.long_encoding == LongEncoding::SLI {
+            Writer::sli_int64_size(*self)
+        } else {
+            Writer::var_int64_size(*self)
+        }
+    }
+
+    fn vec_size(value: &[Self], serializer: &mut SerializerState) -> usize {
+        le_array_size(value, serializer)
+    }
+
+    fn reserved_space() -> usize {
+        // the sli encoding takes one more byte for big values
+        mem::size_of::<i64>() + 1
+    }
+}
+
+/// The most compact encoding of a string and its number of bytes.
+///
+/// Latin1 is taken for ascii as well, it has the same bytes as utf8 and Java reads it faster.
+fn string_flag(value: &str) -> (StringFlag, usize) {
+    if value.chars().all(|c| (c as u32) <= 0xff) {
+        (StringFlag::LATIN1, value.chars().count())
+    } else {
+        let utf16_len = value.encode_utf16().count() * 2;
+        if utf16_len < value.len() {
+            (StringFlag::UTF16, utf16_len)
+        } else {
+            (StringFlag::UTF8, value.len())
+        }
+    }
+}
+
 /// The implement of String Type
+///
+/// Strings are utf8 by default, `Config::compress_string` writes the coder byte of Java in front.
 impl Serialize for String {
     fn write(&self, serializer: &mut SerializerState) {
-        serializer.writer.var_int32(self.len() as i32);
-        serializer.writer.bytes(self.as_bytes());
+        if !serializer.config.compress_string {
+            serializer.writer.var_uint32(self.len() as u32);
+            serializer.writer.bytes(self.as_bytes());
+            return;
+        }
+        let (flag, len) = string_flag(self);
+        serializer.writer.u8(flag as u8);
+        serializer.writer.var_uint32(len as u32);
+        match flag {
+            StringFlag::LATIN1 if self.is_ascii() => serializer.writer.bytes(self.as_bytes()),
+            StringFlag::LATIN1 => self.chars().for_each(|c| serializer.writer.u8(c as u8)),
+            StringFlag::UTF16 => self.encode_utf16().for_each(|c| serializer.writer.u16(c)),
+            StringFlag::UTF8 => serializer.writer.bytes(self.as_bytes()),
+        }
     }
 
-    fn write_vec(value: &Vec<Self>, serializer: &mut SerializerState) {
-        serializer.writer.var_int32(value.len() as i32);
+    fn write_vec(value: &[Self], serializer: &mut SerializerState) {
+        serializer.writer.var_uint32(value.len() as u32);
         serializer
             .writer
             .reserve((<Self as Serialize>::reserved_space()) * value.len());
@@ -169,6 +406,21 @@ impl Serialize for String {
         }
     }
 
+    fn size(&self, serializer: &mut SerializerState) -> usize {
+        if serializer.config.compress_string {
+            let (_, len) = string_flag(self);
+            // coder, length and the bytes
+            1 + Writer::var_uint32_size(len as u32) + len
+        } else {
+            Writer::var_uint32_size(self.len() as u32) + self.len()
+        }
+    }
+
+    fn vec_size(value: &[Self], serializer: &mut SerializerState) -> usize {
+        Writer::var_uint32_size(value.len() as u32)
+            + value.iter().map(|x| x.size(serializer)).sum::<usize>()
+    }
+
     fn reserved_space() -> usize {
         mem::size_of::<i32>()
     }
@@ -179,9 +431,16 @@ impl Serialize for bool {
         serializer.writer.u8(if *self { 1 } else { 0 });
     }
 
-    fn write_vec(value: &Vec<Self>, serializer: &mut SerializerState) {
-        serializer.writer.var_int32(value.len() as i32);
-        serializer.writer.bytes(to_u8_slice(value.as_slice()));
+    fn write_vec(value: &[Self], serializer: &mut SerializerState) {
+        serializer.write_buffer(to_u8_slice(value));
+    }
+
+    fn size(&self, _serializer: &mut SerializerState) -> usize {
+        mem::size_of::<u8>()
+    }
+
+    fn vec_size(value: &[Self], serializer: &mut SerializerState) -> usize {
+        le_array_size(value, serializer)
     }
 
     fn reserved_space() -> usize {
@@ -189,53 +448,126 @@ impl Serialize for bool {
     }
 }
 
-impl<T1: Serialize, T2: Serialize> Serialize for HashMap<T1, T2> {
-    fn write(&self, serializer: &mut SerializerState) {
-        // length
-        serializer.writer.var_int32(self.len() as i32);
+/// Write the length of a map and its entries, each key and value with its own head.
+fn write_map<'a, T1: Serialize + 'a, T2: Serialize + 'a>(
+    len: usize,
+    entries: impl Iterator<Item = (&'a T1, &'a T2)>,
+    serializer: &mut SerializerState,
+) {
+    // length
+    serializer.writer.var_uint32(len as u32);
 
-        let reserved_space = (<T1 as Serialize>::reserved_space() + SIZE_OF_REF_AND_TYPE)
-            * self.len()
-            + (<T2 as Serialize>::reserved_space() + SIZE_OF_REF_AND_TYPE) * self.len();
-        serializer.writer.reserve(reserved_space);
+    let reserved_space = (<T1 as Serialize>::reserved_space() + SIZE_OF_REF_AND_TYPE) * len
+        + (<T2 as Serialize>::reserved_space() + SIZE_OF_REF_AND_TYPE) * len;
+    serializer.writer.reserve(reserved_space);
 
-        // key-value
-        for i in self.iter() {
-            i.0.serialize(serializer);
-            i.1.serialize(serializer);
-        }
+    // key-value
+    for (key, value) in entries {
+        key.serialize(serializer);
+        value.serialize(serializer);
     }
+}
 
-    fn reserved_space() -> usize {
-        mem::size_of::<i32>()
+/// The exact number of bytes written by write_map.
+fn map_size<'a, T1: Serialize + 'a, T2: Serialize + 'a>(
+    len: usize,
+    entries: impl Iterator<Item = (&'a T1, &'a T2)>,
+    serializer: &mut SerializerState,
+) -> usize {
+    Writer::var_uint32_size(len as u32)
+        + entries
+            .map(|(key, value)| key.serialized_size(serializer) + value.serialized_size(serializer))
+            .sum::<usize>()
+}
+
+/// Write the length of a set or a list and its elements, each with its own head.
+fn write_elements<'a, T: Serialize + 'a>(
+    len: usize,
+    elements: impl Iterator<Item = &'a T>,
+    serializer: &mut SerializerState,
+) {
+    // length
+    serializer.writer.var_uint32(len as u32);
+
+    let reserved_space = (<T as Serialize>::reserved_space() + SIZE_OF_REF_AND_TYPE) * len;
+    serializer.writer.reserve(reserved_space);
+
+    for element in elements {
+        element.serialize(serializer);
     }
 }
 
-impl<T: Serialize> Serialize for HashSet<T> {
-    fn write(&self, serializer: &mut SerializerState) {
-        // length
-        serializer.writer.i32(self.len() as i32);
+/// The exact number of bytes written by write_elements.
+fn elements_size<'a, T: Serialize + 'a>(
+    len: usize,
+    elements: impl Iterator<Item = &'a T>,
+    serializer: &mut SerializerState,
+) -> usize {
+    Writer::var_uint32_size(len as u32)
+        + elements
+            .map(|element| element.serialized_size(serializer))
+            .sum::<usize>()
+}
+
+/// Implement Serialize for the maps, they are all written as MAP.
+macro_rules! impl_map_serialize {
+    ($map: ident $(, $hasher: ident)?) => {
+        impl<T1: Serialize, T2: Serialize $(, $hasher)?> Serialize for $map<T1, T2 $(, $hasher)?> {
+            fn write(&self, serializer: &mut SerializerState) {
+                write_map(self.len(), self.iter(), serializer);
+            }
 
-        let reserved_space =
-            (<T as Serialize>::reserved_space() + SIZE_OF_REF_AND_TYPE) * self.len();
-        serializer.writer.reserve(reserved_space);
+            fn size(&self, serializer: &mut SerializerState) -> usize {
+                map_size(self.len(), self.iter(), serializer)
+            }
 
-        // key-value
-        for i in self.iter() {
-            i.serialize(serializer);
+            fn reserved_space() -> usize {
+                mem::size_of::<i32>()
+            }
         }
-    }
+    };
+}
 
-    fn reserved_space() -> usize {
-        mem::size_of::<i32>()
-    }
+/// Implement Serialize for the sets and the lists which have no primitive array, they are written with headed elements.
+macro_rules! impl_elements_serialize {
+    ($collection: ident $(, $hasher: ident)?) => {
+        impl<T: Serialize $(, $hasher)?> Serialize for $collection<T $(, $hasher)?> {
+            fn write(&self, serializer: &mut SerializerState) {
+                write_elements(self.len(), self.iter(), serializer);
+            }
+
+            fn size(&self, serializer: &mut SerializerState) -> usize {
+                elements_size(self.len(), self.iter(), serializer)
+            }
+
+            fn reserved_space() -> usize {
+                mem::size_of::<i32>()
+            }
+        }
+    };
 }
 
+impl_map_serialize!(HashMap, S);
+impl_map_serialize!(BTreeMap);
+#[cfg(feature = "indexmap")]
+impl_map_serialize!(IndexMap, S);
+impl_elements_serialize!(HashSet, S);
+impl_elements_serialize!(BTreeSet);
+#[cfg(feature = "indexmap")]
+impl_elements_serialize!(IndexSet, S);
+impl_elements_serialize!(VecDeque);
+impl_elements_serialize!(LinkedList);
+impl_elements_serialize!(BinaryHeap);
+
 impl Serialize for NaiveDateTime {
     fn write(&self, serializer: &mut SerializerState) {
         serializer.writer.u64(self.timestamp_millis() as u64);
     }
 
+    fn size(&self, _serializer: &mut SerializerState) -> usize {
+        mem::size_of::<u64>()
+    }
+
     fn reserved_space() -> usize {
         mem::size_of::<u64>()
     }
@@ -251,6 +583,10 @@ impl Serialize for NaiveDate {
         serializer.writer.u64(days_since_epoch as u64);
     }
 
+    fn size(&self, _serializer: &mut SerializerState) -> usize {
+        mem::size_of::<u64>()
+    }
+
     fn reserved_space() -> usize {
         mem::size_of::<u64>()
     }
@@ -264,12 +600,48 @@ where
         T::write_vec(self, serializer);
     }
 
+    fn size(&self, serializer: &mut SerializerState) -> usize {
+        T::vec_size(self, serializer)
+    }
+
     fn reserved_space() -> usize {
         // size of the vec
         mem::size_of::<u32>()
     }
 }
 
+/// A fixed-size array is written exactly like a Vec, as the primitive array of its elements if they have one.
+impl<T: Serialize, const N: usize> Serialize for [T; N] {
+    fn write(&self, serializer: &mut SerializerState) {
+        T::write_vec(self, serializer);
+    }
+
+    fn size(&self, serializer: &mut SerializerState) -> usize {
+        T::vec_size(self, serializer)
+    }
+
+    fn reserved_space() -> usize {
+        // size of the array
+        mem::size_of::<u32>()
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl Serialize for bytes::Bytes {
+    fn write(&self, serializer: &mut SerializerState) {
+        serializer.write_buffer(self);
+    }
+
+    fn size(&self, serializer: &mut SerializerState) -> usize {
+        serializer.buffer_size(self.len())
+    }
+
+    fn reserved_space() -> usize {
+        // size of the binary
+        mem::size_of::<u32>()
+    }
+}
+
 impl<T> Serialize for Option<T>
 where
     T: Serialize,
@@ -282,69 +654,538 @@ where
         }
     }
 
-    fn serialize(&self, serializer: &mut SerializerState) {
+    fn size(&self, serializer: &mut SerializerState) -> usize {
+        if let Some(v) = self {
+            T::size(v, serializer)
+        } else {
+            unreachable!("size should be call by serialized_size")
+        }
+    }
+
+    fn is_null(&self) -> bool {
+        self.is_none()
+    }
+
+    fn serialized_size(&self, serializer: &mut SerializerState) -> usize {
         match self {
-            Some(v) => {
-                // ref flag
-                serializer.writer.i8(RefFlag::NotNullValueFlag as i8);
-                // type
-                serializer.writer.i16(<Self as FuryMeta>::ty() as i16);
+            Some(v) => v.serialized_size(serializer),
+            // only the ref flag
+            None => 1,
+        }
+    }
 
-                v.write(serializer);
-            }
+    fn serialize(&self, serializer: &mut SerializerState) {
+        match self {
+            Some(v) => v.serialize(serializer),
             None => {
                 serializer.writer.i8(RefFlag::NullFlag as i8);
             }
         }
     }
 
+    /// A field of the native mode keeps the flag of every Option, so they can be nested.
+    fn serialize_field(&self, serializer: &mut SerializerState) {
+        match self {
+            Some(v) if serializer.config.native => {
+                serializer.writer.i8(RefFlag::NotNullValueFlag as i8);
+                v.serialize_field(serializer);
+            }
+            _ => self.serialize(serializer),
+        }
+    }
+
+    fn field_size(&self, serializer: &mut SerializerState) -> usize {
+        match self {
+            Some(v) if serializer.config.native => 1 + v.field_size(serializer),
+            _ => self.serialized_size(serializer),
+        }
+    }
+
     fn reserved_space() -> usize {
         mem::size_of::<T>()
     }
 }
 
+/// A Result is a flag, 0 for Ok and 1 for Err, and the value written as a field.
+///
+/// Only Rust knows the type, it is meant for the native mode.
+impl<T: Serialize, E: Serialize> Serialize for Result<T, E> {
+    fn write(&self, serializer: &mut SerializerState) {
+        match self {
+            Ok(v) => {
+                serializer.writer.u8(0);
+                v.serialize_field(serializer);
+            }
+            Err(e) => {
+                serializer.writer.u8(1);
+                e.serialize_field(serializer);
+            }
+        }
+    }
+
+    fn size(&self, serializer: &mut SerializerState) -> usize {
+        1 + match self {
+            Ok(v) => v.field_size(serializer),
+            Err(e) => e.field_size(serializer),
+        }
+    }
+
+    fn reserved_space() -> usize {
+        1 + T::reserved_space().max(E::reserved_space())
+    }
+}
+
+/// A tuple is written as a list of its elements, each with its own head.
+macro_rules! impl_tuple_serialize {
+    ($len: expr, $($name: ident $idx: tt),+) => {
+        impl<$($name: Serialize),+> Serialize for ($($name,)+) {
+            fn write(&self, serializer: &mut SerializerState) {
+                serializer.writer.var_uint32($len);
+                $(self.$idx.serialize(serializer);)+
+            }
+
+            fn size(&self, serializer: &mut SerializerState) -> usize {
+                Writer::var_uint32_size($len) $(+ self.$idx.serialized_size(serializer))+
+            }
+
+            fn reserved_space() -> usize {
+                mem::size_of::<u32>() $(+ $name::reserved_space() + SIZE_OF_REF_AND_TYPE)+
+            }
+        }
+    };
+}
+
+impl_tuple_serialize!(1, A 0);
+impl_tuple_serialize!(2, A 0, B 1);
+impl_tuple_serialize!(3, A 0, B 1, C 2);
+impl_tuple_serialize!(4, A 0, B 1, C 2, D 3);
+impl_tuple_serialize!(5, A 0, B 1, C 2, D 3, E 4);
+impl_tuple_serialize!(6, A 0, B 1, C 2, D 3, E 4, F 5);
+impl_tuple_serialize!(7, A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_tuple_serialize!(8, A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+impl_tuple_serialize!(9, A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+impl_tuple_serialize!(10, A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+impl_tuple_serialize!(11, A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+impl_tuple_serialize!(12, A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
+
+/// Rc and Arc are tracked by the ref table, an object which is referenced again is written as its ref id.
+///
+/// A null value like `Rc<Option<T>>` holding None is written as null, it isn't tracked.
+macro_rules! impl_shared_serialize {
+    ($ptr: ident) => {
+        impl<T: Serialize> Serialize for $ptr<T> {
+            fn write(&self, serializer: &mut SerializerState) {
+                T::write(self, serializer);
+            }
+
+            fn size(&self, serializer: &mut SerializerState) -> usize {
+                T::size(self, serializer)
+            }
+
+            fn is_null(&self) -> bool {
+                T::is_null(self)
+            }
+
+            fn write_type_id(&self, serializer: &mut SerializerState) {
+                T::write_type_id(self, serializer);
+            }
+
+            fn type_id_size(&self) -> usize {
+                T::type_id_size(self)
+            }
+
+            fn serialize(&self, serializer: &mut SerializerState) {
+                if self.is_null() {
+                    serializer.writer.i8(RefFlag::NullFlag as i8);
+                    return;
+                }
+                match serializer.track_ref($ptr::as_ptr(self) as usize) {
+                    Some(id) => {
+                        serializer.writer.i8(RefFlag::RefFlag as i8);
+                        serializer.writer.var_uint32(id);
+                    }
+                    None => {
+                        serializer.writer.i8(RefFlag::RefValueFlag as i8);
+                        self.write_type_id(serializer);
+                        self.write(serializer);
+                    }
+                }
+            }
+
+            fn serialized_size(&self, serializer: &mut SerializerState) -> usize {
+                if self.is_null() {
+                    // only the ref flag
+                    return 1;
+                }
+                match serializer.track_ref($ptr::as_ptr(self) as usize) {
+                    Some(id) => 1 + Writer::var_uint32_size(id),
+                    None => 1 + self.type_id_size() + self.size(serializer),
+                }
+            }
+
+            fn serialize_field(&self, serializer: &mut SerializerState) {
+                // the ref flag is needed in the native mode too
+                self.serialize(serializer);
+            }
+
+            fn field_size(&self, serializer: &mut SerializerState) -> usize {
+                self.serialized_size(serializer)
+            }
+
+            fn reserved_space() -> usize {
+                // the object may be written as a ref id only, and T may refer back to this type
+                mem::size_of::<u32>()
+            }
+        }
+    };
+}
+
+impl_shared_serialize!(Rc);
+impl_shared_serialize!(Arc);
+
+/// The cell is borrowed while it is written, it must not be borrowed mutably meanwhile.
+impl<T: Serialize> Serialize for RefCell<T> {
+    fn write(&self, serializer: &mut SerializerState) {
+        self.borrow().write(serializer);
+    }
+
+    fn size(&self, serializer: &mut SerializerState) -> usize {
+        self.borrow().size(serializer)
+    }
+
+    fn is_null(&self) -> bool {
+        self.borrow().is_null()
+    }
+
+    fn reserved_space() -> usize {
+        T::reserved_space()
+    }
+}
+
+/// A Weak is written as the Rc it points to, or as null once the object is dropped.
+impl<T: Serialize> Serialize for Weak<T> {
+    fn write(&self, serializer: &mut SerializerState) {
+        if let Some(v) = self.upgrade() {
+            v.write(serializer)
+        } else {
+            unreachable!("write should be call by serialize")
+        }
+    }
+
+    fn size(&self, serializer: &mut SerializerState) -> usize {
+        if let Some(v) = self.upgrade() {
+            v.size(serializer)
+        } else {
+            unreachable!("size should be call by serialized_size")
+        }
+    }
+
+    fn serialize(&self, serializer: &mut SerializerState) {
+        match self.upgrade() {
+            Some(v) => v.serialize(serializer),
+            None => serializer.writer.i8(RefFlag::NullFlag as i8),
+        }
+    }
+
+    fn serialized_size(&self, serializer: &mut SerializerState) -> usize {
+        match self.upgrade() {
+            Some(v) => v.serialized_size(serializer),
+            // only the ref flag
+            None => 1,
+        }
+    }
+
+    fn serialize_field(&self, serializer: &mut SerializerState) {
+        self.serialize(serializer);
+    }
+
+    fn field_size(&self, serializer: &mut SerializerState) -> usize {
+        self.serialized_size(serializer)
+    }
+
+    fn reserved_space() -> usize {
+        // the object may be written as a ref id only, and T may refer back to this type
+        mem::size_of::<u32>()
+    }
+}
+
+/// A binary or a primitive array given to the buffer callback, the same as `BufferObject` of pyfury.
+///
+/// The bytes are only borrowed while the callback runs, it copies them or writes them out itself.
+pub struct BufferObject<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> BufferObject<'a> {
+    pub fn total_bytes(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// The bytes of the buffer, the elements of a primitive array are little-endian.
+    pub fn as_slice(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn to_buffer(&self) -> Vec<u8> {
+        self.bytes.to_vec()
+    }
+}
+
+/// Decides whether a buffer is written in band, the ones it returns false for are left out of the payload.
+pub type BufferCallback<'se> = Box<dyn FnMut(BufferObject) -> bool + 'se>;
+
+/// Size of the head: bitmap, language, native offset and native size.
+const HEAD_SIZE: usize = 1 + 1 + 4 + 4;
+
+/// The number of tags and shared objects of a record which are tracked without allocating.
+const INLINE_ENTRIES: usize = 8;
+
+/// The tags written in a record, a tag is referred to by its position.
+///
+/// The first ones are kept inline and looked up by a linear scan, like the rest.
+#[derive(Default)]
+struct TagTable {
+    inline: [Cow<'static, str>; INLINE_ENTRIES],
+    spilled: Vec<Cow<'static, str>>,
+    len: usize,
+}
+
+impl TagTable {
+    fn position(&self, tag: &str) -> Option<usize> {
+        self.inline[..self.len.min(INLINE_ENTRIES)]
+            .iter()
+            .chain(self.spilled.iter())
+            .position(|x| x == tag)
+    }
+
+    fn push(&mut self, tag: Cow<'static, str>) {
+        if self.len < INLINE_ENTRIES {
+            self.inline[self.len] = tag;
+        } else {
+            self.spilled.push(tag);
+        }
+        self.len += 1;
+    }
+
+    fn clear(&mut self) {
+        // drop the owned tags, the inline slots are reused
+        for tag in self.inline[..self.len.min(INLINE_ENTRIES)].iter_mut() {
+            *tag = Cow::Borrowed("");
+        }
+        self.spilled.clear();
+        self.len = 0;
+    }
+}
+
+/// The ref ids of the shared objects written in a record by their address, the ids are given in order.
+///
+/// The first ones are kept inline and looked up by a linear scan, the rest are hashed.
+#[derive(Default)]
+struct RefTable {
+    inline: [usize; INLINE_ENTRIES],
+    spilled: HashMap<usize, u32>,
+    len: usize,
+}
+
+impl RefTable {
+    fn track(&mut self, address: usize) -> Option<u32> {
+        let inline = &self.inline[..self.len.min(INLINE_ENTRIES)];
+        if let Some(id) = inline.iter().position(|x| *x == address) {
+            return Some(id as u32);
+        }
+        if let Some(id) = self.spilled.get(&address) {
+            return Some(*id);
+        }
+        if self.len < INLINE_ENTRIES {
+            self.inline[self.len] = address;
+        } else {
+            self.spilled.insert(address, self.len as u32);
+        }
+        self.len += 1;
+        None
+    }
+
+    fn clear(&mut self) {
+        self.spilled.clear();
+        self.len = 0;
+    }
+}
+
+/// The state of one serialization, it can be reused for many records.
+///
+/// Reusing the state keeps the allocations of the writer and the tag table,
+/// so serializing small messages one after another doesn't allocate once it is warm.
+/// ```
+/// use fury::{Config, SerializerState, Writer};
+///
+/// let mut serializer = SerializerState::new(Writer::default(), Config::default());
+/// for i in 0..3 {
+///     serializer.writer.clear();
+///     serializer.write_record(&i.to_string());
+///     let bin: &[u8] = serializer.writer.as_slice();
+/// }
+/// ```
 pub struct SerializerState<'se> {
-    pub writer: &'se mut Writer,
-    pub tags: Vec<&'static str>,
+    pub writer: Writer<'se>,
+    // the tags of derived structs are static, the ones of `Value::Struct` are owned
+    tags: TagTable,
+    // ref ids of the shared objects by their address
+    refs: RefTable,
+    pub config: Config,
+    pub buffer_callback: Option<BufferCallback<'se>>,
 }
 
-impl<'de> SerializerState<'de> {
-    fn new(writer: &mut Writer) -> SerializerState {
+impl<'se> SerializerState<'se> {
+    pub fn new(writer: Writer<'se>, config: Config) -> SerializerState<'se> {
         SerializerState {
             writer,
-            tags: Vec::new(),
+            tags: TagTable::default(),
+            refs: RefTable::default(),
+            config,
+            buffer_callback: None,
+        }
+    }
+
+    /// Write the head and the record, after whatever the writer holds.
+    pub fn write_record<T: Serialize>(&mut self, record: &T) {
+        // tag and ref ids are only valid within one payload
+        self.tags.clear();
+        self.refs.clear();
+        if record.is_null() {
+            // the bitmap only like java, every reader stops at the null flag of the bitmap
+            self.writer
+                .u8(config_flags::IS_NULL_FLAG | Self::version_bits());
+            return;
         }
+        self.head::<T>();
+        // the root is a field too, the native mode drops its head
+        <T as Serialize>::serialize_field(record, self);
     }
 
     pub fn write_tag(&mut self, tag: &'static str) {
+        self.write_tag_with(tag, || Cow::Borrowed(tag));
+    }
+
+    /// Write a tag which is only known at runtime, like the one of a `Value::Struct`.
+    pub fn write_owned_tag(&mut self, tag: &str) {
+        self.write_tag_with(tag, || Cow::Owned(tag.to_string()));
+    }
+
+    /// The id of a tag which is written before in the record, a new tag is put into the table.
+    fn tag_id(&mut self, tag: &str, new_tag: impl FnOnce() -> Cow<'static, str>) -> Option<usize> {
+        let idx = self.tags.position(tag);
+        if idx.is_none() {
+            // the following occurrences in the record refer to it by id
+            self.tags.push(new_tag());
+        }
+        idx
+    }
+
+    fn write_tag_with(&mut self, tag: &str, new_tag: impl FnOnce() -> Cow<'static, str>) {
         const USESTRINGVALUE: u8 = 0;
         const USESTRINGID: u8 = 1;
 
-        let mayby_idx = self.tags.iter().position(|x| *x == tag);
-        match mayby_idx {
+        match self.tag_id(tag, new_tag) {
             Some(idx) => {
                 self.writer.u8(USESTRINGID);
                 self.writer.i16(idx as i16);
             }
             None => {
                 self.writer.u8(USESTRINGVALUE);
-                self.writer.skip(8); // todo tag hash
+                self.writer.i64(compute_tag_hash(tag));
                 self.writer.i16(tag.len() as i16);
                 self.writer.bytes(tag.as_bytes());
             }
         };
     }
 
+    /// Write the bytes of a binary or a primitive array.
+    ///
+    /// With a buffer callback a flag tells whether they are in band, they are left out of the payload otherwise.
+    pub fn write_buffer(&mut self, bytes: &[u8]) {
+        if let Some(callback) = self.buffer_callback.as_mut() {
+            let in_band = callback(BufferObject { bytes });
+            self.writer.u8(in_band as u8);
+            if !in_band {
+                return;
+            }
+        }
+        self.writer.var_uint32(bytes.len() as u32);
+        self.writer.bytes(bytes);
+    }
+
+    /// The number of bytes written by write_buffer if the bytes are in band.
+    pub fn buffer_size(&self, len: usize) -> usize {
+        let flag = if self.buffer_callback.is_some() { 1 } else { 0 };
+        flag + Writer::var_uint32_size(len as u32) + len
+    }
+
+    /// Write the name of a field of a struct in the compatible layout.
+    pub fn write_field_name(&mut self, name: &str) {
+        self.writer.var_uint32(name.len() as u32);
+        self.writer.bytes(name.as_bytes());
+    }
+
+    /// The exact number of bytes written by write_field_name.
+    pub fn field_name_size(&self, name: &str) -> usize {
+        Writer::var_uint32_size(name.len() as u32) + name.len()
+    }
+
+    /// The ref id of a shared object which was written before, a new object is given the next id.
+    pub fn track_ref(&mut self, address: usize) -> Option<u32> {
+        self.refs.track(address)
+    }
+
+    /// The exact number of bytes written by write_tag.
+    pub fn tag_size(&mut self, tag: &'static str) -> usize {
+        self.tag_size_with(tag, || Cow::Borrowed(tag))
+    }
+
+    /// The exact number of bytes written by write_owned_tag.
+    pub fn owned_tag_size(&mut self, tag: &str) -> usize {
+        self.tag_size_with(tag, || Cow::Owned(tag.to_string()))
+    }
+
+    fn tag_size_with(&mut self, tag: &str, new_tag: impl FnOnce() -> Cow<'static, str>) -> usize {
+        match self.tag_id(tag, new_tag) {
+            // flag and id
+            Some(_) => 1 + 2,
+            // flag, hash, length and the tag
+            None => 1 + 8 + 2 + tag.len(),
+        }
+    }
+
+    /// The exact number of bytes written by write_record.
+    pub fn record_size<T: Serialize>(&mut self, record: &T) -> usize {
+        self.tags.clear();
+        self.refs.clear();
+        if record.is_null() {
+            return 1;
+        }
+        HEAD_SIZE + record.field_size(self)
+    }
+
+    fn version_bits() -> u8 {
+        config_flags::HEADER_VERSION << config_flags::VERSION_SHIFT
+    }
+
     fn head<T: Serialize>(&mut self) -> &Self {
-        const HEAD_SIZE: usize = 10;
         self.writer
             .reserve(<T as Serialize>::reserved_space() + SIZE_OF_REF_AND_TYPE + HEAD_SIZE);
 
-        let mut bitmap = 0;
+        let mut bitmap = Self::version_bits();
         bitmap |= config_flags::IS_LITTLE_ENDIAN_FLAG;
         bitmap |= config_flags::IS_CROSS_LANGUAGE_FLAG;
+        if self.buffer_callback.is_some() {
+            bitmap |= config_flags::IS_OUT_OF_BAND_FLAG;
+        }
         self.writer.u8(bitmap);
-        self.writer.u8(Language::XLANG as u8);
+        let language = if self.config.native {
+            Language::RUST
+        } else {
+            Language::XLANG
+        };
+        self.writer.u8(language as u8);
         self.writer.skip(4); // native offset
         self.writer.skip(4); // native size
         self
@@ -352,9 +1193,154 @@ impl<'de> SerializerState<'de> {
 }
 
 pub fn to_buffer<T: Serialize>(record: &T) -> Vec<u8> {
-    let mut writer = Writer::default();
-    let mut serializer = SerializerState::new(&mut writer);
-    serializer.head::<T>();
-    <T as Serialize>::serialize(record, &mut serializer);
-    writer.dump()
+    to_buffer_with_config(record, &Config::default())
+}
+
+pub fn to_buffer_with_config<T: Serialize>(record: &T, config: &Config) -> Vec<u8> {
+    let mut serializer = SerializerState::new(Writer::default(), config.clone());
+    // the buffer is allocated once with the exact size
+    let size = serializer.record_size(record);
+    serializer.writer.reserve(size);
+    serializer.write_record(record);
+    serializer.writer.into_inner()
+}
+
+/// Serialize the record with out-of-band buffers, the same as `buffer_callback` of pyfury.
+///
+/// Binaries and primitive arrays are given to `callback`, the ones it returns false for are left out of the payload.
+/// They must be given to `from_buffer_with_buffers` in the same order.
+/// ```
+/// let value = vec![vec![1u8; 1024], vec![2u8; 16]];
+/// let mut buffers = Vec::new();
+/// let bin = fury::to_buffer_with_callback(&value, |buffer| {
+///     // only the big ones are out of band
+///     if buffer.total_bytes() < 64 {
+///         return true;
+///     }
+///     buffers.push(buffer.to_buffer());
+///     false
+/// });
+/// assert!(bin.len() < 64);
+///
+/// let buffers: Vec<&[u8]> = buffers.iter().map(Vec::as_slice).collect();
+/// let obj: Vec<Vec<u8>> = fury::from_buffer_with_buffers(&bin, &buffers).unwrap();
+/// assert_eq!(obj, value);
+/// ```
+pub fn to_buffer_with_callback<T: Serialize, F: FnMut(BufferObject) -> bool>(
+    record: &T,
+    callback: F,
+) -> Vec<u8> {
+    to_buffer_with_callback_and_config(record, &Config::default(), callback)
+}
+
+pub fn to_buffer_with_callback_and_config<T: Serialize, F: FnMut(BufferObject) -> bool>(
+    record: &T,
+    config: &Config,
+    callback: F,
+) -> Vec<u8> {
+    let mut serializer = SerializerState::new(Writer::default(), config.clone());
+    serializer.buffer_callback = Some(Box::new(callback));
+    // the buffers are taken as in band, so it is the most the payload takes
+    let size = serializer.record_size(record);
+    serializer.writer.reserve(size);
+    serializer.write_record(record);
+    serializer.writer.into_inner()
+}
+
+/// Serialize the record into the start of `bf` without allocating, the number of bytes written is returned.
+///
+/// A record which doesn't fit gives `Error::BufferFull` with the needed size.
+/// Only a record with more than 8 distinct tags or 8 shared objects, or with the owned tag of a `Value::Struct`, allocates.
+/// ```
+/// let mut bf = [0u8; 64];
+/// let len = fury::to_slice(&"hello".to_string(), &mut bf).unwrap();
+/// let value: String = fury::from_buffer(&bf[..len]).unwrap();
+/// assert_eq!(value, "hello");
+/// assert!(fury::to_slice(&"hello".to_string(), &mut bf[..8]).is_err());
+/// ```
+pub fn to_slice<T: Serialize>(record: &T, bf: &mut [u8]) -> Result<usize, Error> {
+    to_slice_with_config(record, bf, &Config::default())
+}
+
+pub fn to_slice_with_config<T: Serialize>(
+    record: &T,
+    bf: &mut [u8],
+    config: &Config,
+) -> Result<usize, Error> {
+    let mut serializer = SerializerState::new(Writer::from(bf), config.clone());
+    serializer.write_record(record);
+    serializer.writer.flush()?;
+    Ok(serializer.writer.len())
+}
+
+/// The exact number of bytes `to_buffer` produces for the record.
+///
+/// It can be used to size a frame or a shared memory slot before serializing.
+/// ```
+/// let value = vec!["hello".to_string()];
+/// assert_eq!(fury::serialized_size(&value), fury::to_buffer(&value).len());
+/// ```
+pub fn serialized_size<T: Serialize>(record: &T) -> usize {
+    serialized_size_with_config(record, &Config::default())
+}
+
+pub fn serialized_size_with_config<T: Serialize>(record: &T, config: &Config) -> usize {
+    SerializerState::new(Writer::default(), config.clone()).record_size(record)
+}
+
+/// Serialize the record after the bytes in `bf`, reusing its allocation.
+pub fn to_buffer_into<T: Serialize>(record: &T, bf: &mut Vec<u8>) {
+    let mut serializer = SerializerState::new(Writer::from(mem::take(bf)), Config::default());
+    serializer.write_record(record);
+    *bf = serializer.writer.into_inner();
+}
+
+/// Serialize the record into a sink, flushing it in chunks while writing.
+///
+/// Only about one chunk of the payload is held in memory, big binaries are passed to the sink without copying.
+pub fn to_writer<T: Serialize, W: io::Write>(record: &T, mut sink: W) -> Result<(), Error> {
+    to_writer_with_config(record, &mut sink, &Config::default())
+}
+
+pub fn to_writer_with_config<T: Serialize, W: io::Write>(
+    record: &T,
+    mut sink: W,
+    config: &Config,
+) -> Result<(), Error> {
+    let mut serializer = SerializerState::new(Writer::with_sink(&mut sink), config.clone());
+    serializer.write_record(record);
+    serializer.writer.flush()
+}
+
+/// Serialize the record into a `BytesMut` or any other `BufMut`.
+///
+/// A `BufMut` of a fixed capacity which the record doesn't fit gives `Error::BufferFull` with
+/// the needed size, the bytes which fit are written already.
+/// ```
+/// let mut bf = bytes::BytesMut::new();
+/// fury::to_buf_mut(&vec![1u8, 2, 3], &mut bf).unwrap();
+/// let value: Vec<u8> = fury::from_buffer(&bf).unwrap();
+/// assert_eq!(value, vec![1, 2, 3]);
+/// ```
+#[cfg(feature = "bytes")]
+pub fn to_buf_mut<T: Serialize, B: bytes::BufMut>(record: &T, bf: &mut B) -> Result<(), Error> {
+    to_buf_mut_with_config(record, bf, &Config::default())
+}
+
+#[cfg(feature = "bytes")]
+pub fn to_buf_mut_with_config<T: Serialize, B: bytes::BufMut>(
+    record: &T,
+    bf: &mut B,
+    config: &Config,
+) -> Result<(), Error> {
+    use bytes::BufMut;
+    let capacity = bf.remaining_mut();
+    // the writer of a full BufMut writes nothing, which write_all reports as WriteZero
+    match to_writer_with_config(record, bf.writer(), config) {
+        Err(Error::Io(e)) if e.kind() == io::ErrorKind::WriteZero => Err(Error::BufferFull {
+            needed: serialized_size_with_config(record, config),
+            capacity,
+        }),
+        result => result,
+    }
 }
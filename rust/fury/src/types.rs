@@ -13,12 +13,18 @@
 // limitations under the License.
 
 use std::{
-    collections::{HashMap, HashSet},
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, LinkedList, VecDeque},
     mem,
+    rc::{Rc, Weak},
+    sync::Arc,
 };
 
 use chrono::{NaiveDate, NaiveDateTime};
+#[cfg(feature = "indexmap")]
+use indexmap::{IndexMap, IndexSet};
 
+use crate::murmur3::murmurhash3_x64_128;
 use crate::Error;
 
 pub trait FuryMeta {
@@ -36,8 +42,12 @@ pub trait FuryMeta {
         ""
     }
 
-    fn is_vec() -> bool {
-        false
+    /// The type id written in front of the value.
+    ///
+    /// It is `ty` for most types, a Vec is written with the array type of its elements
+    /// while it is hashed as a list.
+    fn field_ty() -> FieldType {
+        Self::ty()
     }
 }
 
@@ -65,18 +75,35 @@ macro_rules! impl_primitive_array_meta {
     };
 }
 
-impl<T1, T2> FuryMeta for HashMap<T1, T2> {
+impl<T, E> FuryMeta for Result<T, E> {
     fn ty() -> FieldType {
-        FieldType::MAP
+        FieldType::RustResult
     }
 }
 
-impl<T> FuryMeta for HashSet<T> {
-    fn ty() -> FieldType {
-        FieldType::FurySet
-    }
+macro_rules! impl_collection_meta {
+    ($ty: expr, $collection: ident<$($param: ident),+>) => {
+        impl<$($param),+> FuryMeta for $collection<$($param),+> {
+            fn ty() -> FieldType {
+                $ty
+            }
+        }
+    };
 }
 
+impl_collection_meta!(FieldType::MAP, HashMap<T1, T2, S>);
+impl_collection_meta!(FieldType::MAP, BTreeMap<T1, T2>);
+#[cfg(feature = "indexmap")]
+impl_collection_meta!(FieldType::MAP, IndexMap<T1, T2, S>);
+impl_collection_meta!(FieldType::FurySet, HashSet<T, S>);
+impl_collection_meta!(FieldType::FurySet, BTreeSet<T>);
+#[cfg(feature = "indexmap")]
+impl_collection_meta!(FieldType::FurySet, IndexSet<T, S>);
+// the other lists are written with headed elements, even those of primitives
+impl_collection_meta!(FieldType::ARRAY, VecDeque<T>);
+impl_collection_meta!(FieldType::ARRAY, LinkedList<T>);
+impl_collection_meta!(FieldType::ARRAY, BinaryHeap<T>);
+
 impl FuryMeta for u8 {
     fn ty() -> FieldType {
         FieldType::UINT8
@@ -102,6 +129,7 @@ impl_number_meta!(FieldType::UINT16, u16);
 impl_number_meta!(FieldType::UINT32, u32);
 impl_number_meta!(FieldType::UINT64, u64);
 impl_number_meta!(FieldType::INT8, i8);
+impl_number_meta!(FieldType::UINT64, usize);
 
 // special type array
 impl_primitive_array_meta!(FieldType::BOOL, FieldType::FuryPrimitiveBoolArray, bool);
@@ -114,14 +142,76 @@ impl_primitive_array_meta!(FieldType::STRING, FieldType::FuryStringArray, String
 
 impl<T: FuryMeta> FuryMeta for Vec<T> {
     fn ty() -> FieldType {
-        FieldType::ARRAY
+        // Vec<u8> is hashed as binary, the other Vecs as lists even if they are written as primitive arrays
+        match T::vec_ty() {
+            FieldType::BINARY => FieldType::BINARY,
+            _ => FieldType::ARRAY,
+        }
     }
 
-    fn is_vec() -> bool {
-        true
+    fn field_ty() -> FieldType {
+        T::vec_ty()
     }
 }
 
+impl<T: FuryMeta, const N: usize> FuryMeta for [T; N] {
+    fn ty() -> FieldType {
+        <Vec<T> as FuryMeta>::ty()
+    }
+
+    fn field_ty() -> FieldType {
+        <Vec<T> as FuryMeta>::field_ty()
+    }
+}
+
+// Bytes is written exactly like Vec<u8>, so either can be read as the other
+#[cfg(feature = "bytes")]
+impl FuryMeta for bytes::Bytes {
+    fn ty() -> FieldType {
+        <Vec<u8> as FuryMeta>::ty()
+    }
+
+    fn vec_ty() -> FieldType {
+        <Vec<u8> as FuryMeta>::vec_ty()
+    }
+
+    fn field_ty() -> FieldType {
+        <Vec<u8> as FuryMeta>::field_ty()
+    }
+}
+
+// shared pointers and cells are written like the value they hold
+macro_rules! impl_shared_meta {
+    ($ptr: ident) => {
+        impl<T: FuryMeta> FuryMeta for $ptr<T> {
+            fn ty() -> FieldType {
+                T::ty()
+            }
+
+            fn vec_ty() -> FieldType {
+                T::vec_ty()
+            }
+
+            fn hash() -> u32 {
+                T::hash()
+            }
+
+            fn tag() -> &'static str {
+                T::tag()
+            }
+
+            fn field_ty() -> FieldType {
+                T::field_ty()
+            }
+        }
+    };
+}
+
+impl_shared_meta!(Rc);
+impl_shared_meta!(Arc);
+impl_shared_meta!(Weak);
+impl_shared_meta!(RefCell);
+
 impl<T: FuryMeta> FuryMeta for Option<T> {
     fn vec_ty() -> FieldType {
         T::vec_ty()
@@ -130,12 +220,51 @@ impl<T: FuryMeta> FuryMeta for Option<T> {
     fn ty() -> FieldType {
         T::ty()
     }
+
+    fn hash() -> u32 {
+        T::hash()
+    }
+
+    fn tag() -> &'static str {
+        T::tag()
+    }
+
+    fn field_ty() -> FieldType {
+        T::field_ty()
+    }
+}
+
+/// Tuples are lists of their elements, like the tuples of pyfury.
+macro_rules! impl_tuple_meta {
+    ($($name: ident),+) => {
+        impl<$($name),+> FuryMeta for ($($name,)+) {
+            fn ty() -> FieldType {
+                FieldType::ARRAY
+            }
+        }
+    };
 }
 
-#[allow(dead_code)]
+impl_tuple_meta!(A);
+impl_tuple_meta!(A, B);
+impl_tuple_meta!(A, B, C);
+impl_tuple_meta!(A, B, C, D);
+impl_tuple_meta!(A, B, C, D, E);
+impl_tuple_meta!(A, B, C, D, E, F);
+impl_tuple_meta!(A, B, C, D, E, F, G);
+impl_tuple_meta!(A, B, C, D, E, F, G, H);
+impl_tuple_meta!(A, B, C, D, E, F, G, H, I);
+impl_tuple_meta!(A, B, C, D, E, F, G, H, I, J);
+impl_tuple_meta!(A, B, C, D, E, F, G, H, I, J, K);
+impl_tuple_meta!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+/// The coder written in front of a string when `Config::compress_string` is enabled, the same as Java.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum StringFlag {
     LATIN1 = 0,
-    UTF8 = 1,
+    // little-endian code units
+    UTF16 = 1,
+    UTF8 = 2,
 }
 
 pub enum RefFlag {
@@ -177,36 +306,77 @@ pub enum FieldType {
     FuryPrimitiveFloatArray = 262,
     FuryPrimitiveDoubleArray = 263,
     FuryStringArray = 264,
+    FuryEnum = 269,
+    // only written by Rust
+    RustResult = 1024,
+}
+
+impl TryFrom<i16> for FieldType {
+    type Error = Error;
+
+    fn try_from(num: i16) -> Result<Self, Error> {
+        match num {
+            1 => Ok(FieldType::BOOL),
+            2 => Ok(FieldType::UINT8),
+            3 => Ok(FieldType::INT8),
+            4 => Ok(FieldType::UINT16),
+            5 => Ok(FieldType::INT16),
+            6 => Ok(FieldType::UINT32),
+            7 => Ok(FieldType::INT32),
+            8 => Ok(FieldType::UINT64),
+            9 => Ok(FieldType::INT64),
+            11 => Ok(FieldType::FLOAT),
+            12 => Ok(FieldType::DOUBLE),
+            13 => Ok(FieldType::STRING),
+            14 => Ok(FieldType::BINARY),
+            16 => Ok(FieldType::DATE),
+            18 => Ok(FieldType::TIMESTAMP),
+            25 => Ok(FieldType::ARRAY),
+            30 => Ok(FieldType::MAP),
+            256 => Ok(FieldType::FuryTypeTag),
+            257 => Ok(FieldType::FurySet),
+            258 => Ok(FieldType::FuryPrimitiveBoolArray),
+            259 => Ok(FieldType::FuryPrimitiveShortArray),
+            260 => Ok(FieldType::FuryPrimitiveIntArray),
+            261 => Ok(FieldType::FuryPrimitiveLongArray),
+            262 => Ok(FieldType::FuryPrimitiveFloatArray),
+            263 => Ok(FieldType::FuryPrimitiveDoubleArray),
+            264 => Ok(FieldType::FuryStringArray),
+            269 => Ok(FieldType::FuryEnum),
+            1024 => Ok(FieldType::RustResult),
+            _ => Err(Error::FieldTypeId(num)),
+        }
+    }
 }
 
 const MAX_UNT32: u64 = (1 << 31) - 1;
 
+/// The hash of a tag, the same as `TypeUtils.computeStringHash` in Java.
+///
+/// The utf8 bytes are taken as signed like Java does, pyfury gives the same hash for ascii strings.
 pub fn compute_string_hash(s: &str) -> u32 {
-    let mut hash: u64 = 17;
+    let mut hash: i64 = 17;
     s.as_bytes().iter().for_each(|b| {
-        hash = (hash * 31) + (*b as u64);
-        while hash >= MAX_UNT32 {
+        hash = (hash * 31) + (*b as i8 as i64);
+        while hash > MAX_UNT32 as i64 {
             hash /= 7;
         }
     });
     hash as u32
 }
 
-const BASIC_TYPES: [FieldType; 11] = [
-    FieldType::BOOL,
-    FieldType::INT8,
-    FieldType::INT16,
-    FieldType::INT32,
-    FieldType::INT64,
-    FieldType::FLOAT,
-    FieldType::DOUBLE,
-    FieldType::STRING,
-    FieldType::BINARY,
-    FieldType::DATE,
-    FieldType::TIMESTAMP,
-];
-
-pub fn compute_field_hash(hash: u32, id: i16) -> u32 {
+/// The hash written in front of a tag, the same as `EnumStringBytes` in Java.
+pub fn compute_tag_hash(tag: &str) -> i64 {
+    let hash = murmurhash3_x64_128(tag.as_bytes(), 47).0 as i64;
+    // java never writes 0
+    if hash == 0 {
+        1
+    } else {
+        hash
+    }
+}
+
+pub fn compute_field_hash(hash: u32, id: u32) -> u32 {
     let mut new_hash: u64 = (hash as u64) * 31 + (id as u64);
     while new_hash >= MAX_UNT32 {
         new_hash /= 7;
@@ -214,27 +384,66 @@ pub fn compute_field_hash(hash: u32, id: i16) -> u32 {
     new_hash as u32
 }
 
+/// The hash of the fields of a struct, the same as `StructSerializer` in Java and `ComplexObjectSerializer` in pyfury.
+///
+/// `props` are the name, type and tag of the fields in the order they are written.
+/// Every field is folded in with its type id, a nested struct with the hash of its tag.
+/// The element types of lists and maps aren't part of the hash.
+/// Fields of the tag type without a tag, like `Value`, are left out as Java leaves out `Object` fields.
+/// Sets are left out too, neither Java nor pyfury has a type id for them in the hash.
 pub fn compute_struct_hash(props: Vec<(&str, FieldType, &str)>) -> u32 {
     let mut hash = 17;
     props.iter().for_each(|prop| {
-        let (_name, ty, _tag) = prop;
-        hash = match ty {
-            FieldType::ARRAY | FieldType::MAP => compute_field_hash(hash, *ty as i16),
-            _ => hash,
+        let (_name, ty, tag) = prop;
+        let id = match ty {
+            FieldType::FuryTypeTag if tag.is_empty() => return,
+            FieldType::FurySet => return,
+            FieldType::FuryTypeTag => compute_string_hash(tag),
+            _ => *ty as u32,
         };
-        let is_basic_type = BASIC_TYPES.iter().any(|x| *x == *ty);
-        if is_basic_type {
-            hash = compute_field_hash(hash, *ty as i16);
-        }
+        hash = compute_field_hash(hash, id);
+    });
+    hash
+}
+
+/// The hash of the variants of an enum, each is folded in with its name and the hash of its fields.
+///
+/// `variants` are in the order they are declared when the ordinals are written, sorted by name otherwise.
+pub fn compute_enum_hash(variants: Vec<(&str, u32)>) -> u32 {
+    let mut hash = 17;
+    variants.iter().for_each(|(name, fields_hash)| {
+        hash = compute_field_hash(hash, compute_string_hash(name));
+        hash = compute_field_hash(hash, *fields_hash);
     });
     hash
 }
 
+/// Flags of the bitmap, the first byte of every payload.
+///
+/// The header is laid out as:
+/// ```text
+/// | bitmap: u8 | language: u8 | native objects offset: i32 | native objects size: i32 |
+/// ```
+/// Bits of the bitmap:
+/// - 0: the root value is null. Rust and java write the bitmap only then, pyfury still writes
+///   the rest of the header and a null flag. Every reader stops at the bitmap
+/// - 1: little endian
+/// - 2: cross language, the other bytes of the header follow only when it is set
+/// - 3: binaries and primitive arrays may be out of band
+/// - 4..=6: version of the header, bumped on changes of the layout so older Rust readers reject the payload.
+///   Only Rust writes and checks it, java and pyfury leave the bits clear and don't read them
+/// - 7: unused
+///
+/// The language is the one of the writer, java and pyfury write their own.
 pub mod config_flags {
     pub const IS_NULL_FLAG: u8 = 1 << 0;
     pub const IS_LITTLE_ENDIAN_FLAG: u8 = 2;
     pub const IS_CROSS_LANGUAGE_FLAG: u8 = 4;
     pub const IS_OUT_OF_BAND_FLAG: u8 = 8;
+    pub const VERSION_MASK: u8 = 0b0111_0000;
+    pub const VERSION_SHIFT: u8 = 4;
+    /// The version written into the header, the clear bits of java and pyfury read as 0 too.
+    pub const HEADER_VERSION: u8 = 0;
 }
 
 #[derive(Debug, PartialEq)]
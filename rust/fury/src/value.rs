@@ -0,0 +1,395 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::mem;
+use std::rc::Rc;
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::buffer::Writer;
+use crate::deserializer::{Deserialize, DeserializerState};
+use crate::error::Error;
+use crate::serializer::{Serialize, SerializerState};
+use crate::types::{FieldType, FuryMeta, RefFlag};
+
+/// A value of any type, read by the type id in front of it.
+///
+/// It takes the mixed containers java and pyfury send, like `List<Object>` or a dict, as `Vec<Value>`
+/// or `HashMap<String, Value>`. Sets and maps are kept as lists, their elements may be floats or lists.
+///
+/// A struct can only be taken in the compatible layout, which carries the names of its fields.
+/// A value the writer tracked by reference is read as `Shared`, the references to it share its Rc.
+/// ```
+/// use fury::{from_buffer, to_buffer, Value};
+///
+/// let value = vec![Value::Int64(1), Value::String("a".to_string()), Value::Null];
+/// let obj: Vec<Value> = from_buffer(&to_buffer(&value)).unwrap();
+/// assert_eq!(obj, value);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    UInt8(u8),
+    Int8(i8),
+    UInt16(u16),
+    Int16(i16),
+    UInt32(u32),
+    Int32(i32),
+    UInt64(u64),
+    Int64(i64),
+    Float(f32),
+    Double(f64),
+    String(String),
+    Binary(Vec<u8>),
+    Date(NaiveDate),
+    Timestamp(NaiveDateTime),
+    BoolArray(Vec<bool>),
+    Int16Array(Vec<i16>),
+    Int32Array(Vec<i32>),
+    Int64Array(Vec<i64>),
+    FloatArray(Vec<f32>),
+    DoubleArray(Vec<f64>),
+    StringArray(Vec<String>),
+    List(Vec<Value>),
+    Set(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+    Struct {
+        tag: String,
+        fields: Vec<(String, Value)>,
+    },
+    Shared(Rc<Value>),
+}
+
+impl Value {
+    /// The type id the value is written with, a null has none.
+    pub fn field_type(&self) -> Option<FieldType> {
+        Some(match self {
+            Value::Null => return None,
+            Value::Bool(_) => FieldType::BOOL,
+            Value::UInt8(_) => FieldType::UINT8,
+            Value::Int8(_) => FieldType::INT8,
+            Value::UInt16(_) => FieldType::UINT16,
+            Value::Int16(_) => FieldType::INT16,
+            Value::UInt32(_) => FieldType::UINT32,
+            Value::Int32(_) => FieldType::INT32,
+            Value::UInt64(_) => FieldType::UINT64,
+            Value::Int64(_) => FieldType::INT64,
+            Value::Float(_) => FieldType::FLOAT,
+            Value::Double(_) => FieldType::DOUBLE,
+            Value::String(_) => FieldType::STRING,
+            Value::Binary(_) => FieldType::BINARY,
+            Value::Date(_) => FieldType::DATE,
+            Value::Timestamp(_) => FieldType::TIMESTAMP,
+            Value::BoolArray(_) => FieldType::FuryPrimitiveBoolArray,
+            Value::Int16Array(_) => FieldType::FuryPrimitiveShortArray,
+            Value::Int32Array(_) => FieldType::FuryPrimitiveIntArray,
+            Value::Int64Array(_) => FieldType::FuryPrimitiveLongArray,
+            Value::FloatArray(_) => FieldType::FuryPrimitiveFloatArray,
+            Value::DoubleArray(_) => FieldType::FuryPrimitiveDoubleArray,
+            Value::StringArray(_) => FieldType::FuryStringArray,
+            Value::List(_) => FieldType::ARRAY,
+            Value::Set(_) => FieldType::FurySet,
+            Value::Map(_) => FieldType::MAP,
+            Value::Struct { .. } => FieldType::FuryTypeTag,
+            Value::Shared(value) => return value.field_type(),
+        })
+    }
+
+    fn write_body(&self, serializer: &mut SerializerState) {
+        match self {
+            Value::Null => unreachable!("write_body should be call for a non-null value"),
+            Value::Bool(v) => v.write(serializer),
+            Value::UInt8(v) => v.write(serializer),
+            Value::Int8(v) => v.write(serializer),
+            Value::UInt16(v) => v.write(serializer),
+            Value::Int16(v) => v.write(serializer),
+            Value::UInt32(v) => v.write(serializer),
+            Value::Int32(v) => v.write(serializer),
+            Value::UInt64(v) => v.write(serializer),
+            Value::Int64(v) => v.write(serializer),
+            Value::Float(v) => v.write(serializer),
+            Value::Double(v) => v.write(serializer),
+            Value::String(v) => v.write(serializer),
+            Value::Binary(v) => v.write(serializer),
+            Value::Date(v) => v.write(serializer),
+            Value::Timestamp(v) => v.write(serializer),
+            Value::BoolArray(v) => v.write(serializer),
+            Value::Int16Array(v) => v.write(serializer),
+            Value::Int32Array(v) => v.write(serializer),
+            Value::Int64Array(v) => v.write(serializer),
+            Value::FloatArray(v) => v.write(serializer),
+            Value::DoubleArray(v) => v.write(serializer),
+            Value::StringArray(v) => v.write(serializer),
+            Value::List(items) | Value::Set(items) => {
+                serializer.writer.var_uint32(items.len() as u32);
+                for item in items {
+                    item.serialize(serializer);
+                }
+            }
+            Value::Map(entries) => {
+                serializer.writer.var_uint32(entries.len() as u32);
+                for (key, value) in entries {
+                    key.serialize(serializer);
+                    value.serialize(serializer);
+                }
+            }
+            Value::Struct { tag, fields } => {
+                serializer.write_owned_tag(tag);
+                // the compatible layout, the fields can't be hashed without their types
+                serializer.writer.u32(0);
+                serializer.writer.var_uint32(fields.len() as u32);
+                for (name, value) in fields {
+                    serializer.write_field_name(name);
+                    value.serialize(serializer);
+                }
+            }
+            Value::Shared(value) => value.write_body(serializer),
+        }
+    }
+
+    fn body_size(&self, serializer: &mut SerializerState) -> usize {
+        match self {
+            Value::Null => 0,
+            Value::Bool(v) => v.size(serializer),
+            Value::UInt8(v) => v.size(serializer),
+            Value::Int8(v) => v.size(serializer),
+            Value::UInt16(v) => v.size(serializer),
+            Value::Int16(v) => v.size(serializer),
+            Value::UInt32(v) => v.size(serializer),
+            Value::Int32(v) => v.size(serializer),
+            Value::UInt64(v) => v.size(serializer),
+            Value::Int64(v) => v.size(serializer),
+            Value::Float(v) => v.size(serializer),
+            Value::Double(v) => v.size(serializer),
+            Value::String(v) => v.size(serializer),
+            Value::Binary(v) => v.size(serializer),
+            Value::Date(v) => v.size(serializer),
+            Value::Timestamp(v) => v.size(serializer),
+            Value::BoolArray(v) => v.size(serializer),
+            Value::Int16Array(v) => v.size(serializer),
+            Value::Int32Array(v) => v.size(serializer),
+            Value::Int64Array(v) => v.size(serializer),
+            Value::FloatArray(v) => v.size(serializer),
+            Value::DoubleArray(v) => v.size(serializer),
+            Value::StringArray(v) => v.size(serializer),
+            Value::List(items) | Value::Set(items) => {
+                Writer::var_uint32_size(items.len() as u32)
+                    + items
+                        .iter()
+                        .map(|item| item.serialized_size(serializer))
+                        .sum::<usize>()
+            }
+            Value::Map(entries) => {
+                Writer::var_uint32_size(entries.len() as u32)
+                    + entries
+                        .iter()
+                        .map(|(key, value)| {
+                            key.serialized_size(serializer) + value.serialized_size(serializer)
+                        })
+                        .sum::<usize>()
+            }
+            Value::Struct { tag, fields } => {
+                serializer.owned_tag_size(tag)
+                    + 4
+                    + Writer::var_uint32_size(fields.len() as u32)
+                    + fields
+                        .iter()
+                        .map(|(name, value)| {
+                            serializer.field_name_size(name) + value.serialized_size(serializer)
+                        })
+                        .sum::<usize>()
+            }
+            Value::Shared(value) => value.body_size(serializer),
+        }
+    }
+
+    fn read_body(deserializer: &mut DeserializerState, ty: FieldType) -> Result<Self, Error> {
+        Ok(match ty {
+            FieldType::BOOL => Value::Bool(bool::read(deserializer)?),
+            FieldType::UINT8 => Value::UInt8(u8::read(deserializer)?),
+            FieldType::INT8 => Value::Int8(i8::read(deserializer)?),
+            FieldType::UINT16 => Value::UInt16(u16::read(deserializer)?),
+            FieldType::INT16 => Value::Int16(i16::read(deserializer)?),
+            FieldType::UINT32 => Value::UInt32(u32::read(deserializer)?),
+            FieldType::INT32 => Value::Int32(i32::read(deserializer)?),
+            FieldType::UINT64 => Value::UInt64(u64::read(deserializer)?),
+            FieldType::INT64 => Value::Int64(i64::read(deserializer)?),
+            FieldType::FLOAT => Value::Float(f32::read(deserializer)?),
+            FieldType::DOUBLE => Value::Double(f64::read(deserializer)?),
+            FieldType::STRING => Value::String(String::read(deserializer)?),
+            FieldType::BINARY => Value::Binary(Vec::read(deserializer)?),
+            FieldType::DATE => Value::Date(NaiveDate::read(deserializer)?),
+            FieldType::TIMESTAMP => Value::Timestamp(NaiveDateTime::read(deserializer)?),
+            FieldType::FuryPrimitiveBoolArray => Value::BoolArray(Vec::read(deserializer)?),
+            FieldType::FuryPrimitiveShortArray => Value::Int16Array(Vec::read(deserializer)?),
+            FieldType::FuryPrimitiveIntArray => Value::Int32Array(Vec::read(deserializer)?),
+            FieldType::FuryPrimitiveLongArray => Value::Int64Array(Vec::read(deserializer)?),
+            FieldType::FuryPrimitiveFloatArray => Value::FloatArray(Vec::read(deserializer)?),
+            FieldType::FuryPrimitiveDoubleArray => Value::DoubleArray(Vec::read(deserializer)?),
+            FieldType::FuryStringArray => Value::StringArray(Vec::read(deserializer)?),
+            FieldType::ARRAY => Value::List(Self::read_items(deserializer)?),
+            FieldType::FurySet => Value::Set(Self::read_items(deserializer)?),
+            FieldType::MAP => {
+                let len = deserializer.reader.var_uint32()?;
+                let mut entries = Vec::with_capacity(len.min(1024) as usize);
+                for _ in 0..len {
+                    entries.push((
+                        Value::deserialize(deserializer)?,
+                        Value::deserialize(deserializer)?,
+                    ));
+                }
+                Value::Map(entries)
+            }
+            FieldType::FuryTypeTag => {
+                let tag = deserializer.read_tag()?.to_string();
+                if deserializer.reader.u32()? != 0 {
+                    return Err(Error::UntypedStruct(tag));
+                }
+                let len = deserializer.reader.var_uint32()?;
+                let mut fields = Vec::with_capacity(len.min(1024) as usize);
+                for _ in 0..len {
+                    let len = deserializer.reader.var_uint32()?;
+                    let name =
+                        std::str::from_utf8(deserializer.reader.bytes(len as usize)?)?.to_string();
+                    fields.push((name, Value::deserialize(deserializer)?));
+                }
+                Value::Struct { tag, fields }
+            }
+            // the name of the enum isn't written, nor is whether the variant is written by name
+            FieldType::FuryEnum | FieldType::RustResult => return Err(Error::Skip(ty)),
+        })
+    }
+
+    fn read_items(deserializer: &mut DeserializerState) -> Result<Vec<Value>, Error> {
+        let len = deserializer.reader.var_uint32()?;
+        // the length isn't trusted for the allocation, a bad one runs out of bytes instead
+        let mut items = Vec::with_capacity(len.min(1024) as usize);
+        for _ in 0..len {
+            items.push(Value::deserialize(deserializer)?);
+        }
+        Ok(items)
+    }
+}
+
+/// The type of a Value is only known from the value, it has no tag so a field of it isn't part of the struct hash.
+impl FuryMeta for Value {
+    fn ty() -> FieldType {
+        FieldType::FuryTypeTag
+    }
+}
+
+/// The type id is written by `write` along with the value, the serialize function only adds the ref flag.
+impl Serialize for Value {
+    fn write(&self, serializer: &mut SerializerState) {
+        if let Some(ty) = self.field_type() {
+            serializer.writer.i16(ty as i16);
+            self.write_body(serializer);
+        } else {
+            unreachable!("write should be call by serialize")
+        }
+    }
+
+    fn size(&self, serializer: &mut SerializerState) -> usize {
+        mem::size_of::<i16>() + self.body_size(serializer)
+    }
+
+    fn reserved_space() -> usize {
+        // the type id, the other values reserve for themselves
+        mem::size_of::<i16>()
+    }
+
+    fn write_type_id(&self, _serializer: &mut SerializerState) {
+        // written by write
+    }
+
+    fn type_id_size(&self) -> usize {
+        0
+    }
+
+    fn is_null(&self) -> bool {
+        *self == Value::Null
+    }
+
+    fn serialized_size(&self, serializer: &mut SerializerState) -> usize {
+        match self {
+            // only the ref flag
+            Value::Null => 1,
+            Value::Shared(value) => value.serialized_size(serializer),
+            _ => 1 + self.size(serializer),
+        }
+    }
+
+    fn serialize(&self, serializer: &mut SerializerState) {
+        match self {
+            Value::Null => serializer.writer.i8(RefFlag::NullFlag as i8),
+            Value::Shared(value) => value.serialize(serializer),
+            _ => {
+                serializer.writer.i8(RefFlag::NotNullValueFlag as i8);
+                self.write(serializer);
+            }
+        }
+    }
+
+    fn serialize_field(&self, serializer: &mut SerializerState) {
+        // the type id is needed in the native mode too
+        self.serialize(serializer);
+    }
+
+    fn field_size(&self, serializer: &mut SerializerState) -> usize {
+        self.serialized_size(serializer)
+    }
+}
+
+impl Deserialize for Value {
+    fn read(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+        let type_id = deserializer.reader.i16()?;
+        Self::read_with_type(deserializer, type_id)
+    }
+
+    fn read_with_type(deserializer: &mut DeserializerState, type_id: i16) -> Result<Self, Error> {
+        Self::read_body(deserializer, FieldType::try_from(type_id)?)
+    }
+
+    fn read_type_id(_deserializer: &mut DeserializerState) -> Result<(), Error> {
+        // read by read
+        Ok(())
+    }
+
+    fn null() -> Result<Self, Error> {
+        Ok(Value::Null)
+    }
+
+    fn deserialize(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+        let ref_flag = deserializer.reader.i8()?;
+        if ref_flag == (RefFlag::NotNullValueFlag as i8) {
+            Self::read(deserializer)
+        } else if ref_flag == (RefFlag::NullFlag as i8) {
+            Ok(Value::Null)
+        } else if ref_flag == (RefFlag::RefValueFlag as i8) {
+            // the writer may refer to the value again, the Rc is kept like the one of an Rc<Value>
+            let id = deserializer.reserve_ref();
+            Self::read_rc(deserializer, id).map(Value::Shared)
+        } else if ref_flag == (RefFlag::RefFlag as i8) {
+            let id = deserializer.reader.var_uint32()?;
+            deserializer.get_ref::<Rc<Value>>(id).map(Value::Shared)
+        } else {
+            Err(Error::BadRefFlag)
+        }
+    }
+
+    fn deserialize_field(deserializer: &mut DeserializerState) -> Result<Self, Error> {
+        Self::deserialize(deserializer)
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use fury::{
+    from_buffer, to_buffer, to_buffer_into, to_slice, Config, Error, Fury, SerializerState, Writer,
+};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+#[test]
+fn reuse_without_allocation() {
+    let messages: Vec<HashMap<String, Vec<i64>>> = (0..100)
+        .map(|i| HashMap::from([(format!("key{i}"), vec![i; 10])]))
+        .collect();
+
+    let mut serializer = SerializerState::new(Writer::default(), Config::default());
+    let mut bf = Vec::new();
+    let mut allocations = Vec::with_capacity(2);
+    // the first round warms up the buffers
+    for _ in 0..2 {
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        for message in messages.iter() {
+            serializer.writer.clear();
+            serializer.write_record(message);
+            bf.clear();
+            to_buffer_into(message, &mut bf);
+        }
+        allocations.push(ALLOCATIONS.load(Ordering::Relaxed) - before);
+    }
+    assert_eq!(allocations[1], 0);
+
+    assert_eq!(serializer.writer.as_slice(), bf.as_slice());
+    let obj: HashMap<String, Vec<i64>> = from_buffer(&bf).expect("should success");
+    assert_eq!(&obj, messages.last().unwrap());
+}
+
+#[derive(Fury, Debug, PartialEq)]
+#[tag("example.inner")]
+struct Inner {
+    id: i64,
+}
+
+#[derive(Fury, Debug, PartialEq)]
+#[tag("example.outer")]
+struct Outer {
+    name: String,
+    inner: Inner,
+    first: Rc<Inner>,
+    second: Rc<Inner>,
+}
+
+#[test]
+fn slice_without_allocation() {
+    let message = HashMap::from([("key".to_string(), vec![1i64; 10])]);
+    let mut bf = [0u8; 256];
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let len = to_slice(&message, &mut bf).expect("should fit");
+    let small = to_slice(&message, &mut bf[..len - 1]);
+    assert_eq!(ALLOCATIONS.load(Ordering::Relaxed) - before, 0);
+
+    assert_eq!(&bf[..len], to_buffer(&message).as_slice());
+    assert!(matches!(
+        small,
+        Err(Error::BufferFull { needed, capacity }) if needed == len && capacity == len - 1
+    ));
+
+    // the tags and the shared objects are tracked without allocating too
+    let shared = Rc::new(Inner { id: 2 });
+    let message = Outer {
+        name: "outer".to_string(),
+        inner: Inner { id: 1 },
+        first: shared.clone(),
+        second: shared,
+    };
+    // the struct hashes are computed once, on first use
+    to_slice(&message, &mut bf).expect("should fit");
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let len = to_slice(&message, &mut bf).expect("should fit");
+    assert_eq!(ALLOCATIONS.load(Ordering::Relaxed) - before, 0);
+
+    let obj: Outer = from_buffer(&bf[..len]).expect("should success");
+    assert_eq!(obj, message);
+    assert!(Rc::ptr_eq(&obj.first, &obj.second));
+}
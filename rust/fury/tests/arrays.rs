@@ -0,0 +1,87 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use fury::{from_buffer, serialized_size, to_buffer, to_buffer_with_config, Config, Error, Fury};
+
+#[derive(Fury, Debug, PartialEq)]
+#[tag("example.mesh")]
+struct Mesh {
+    hash: [u8; 32],
+    normal: [f32; 3],
+    names: [String; 2],
+    weights: [Option<i64>; 2],
+}
+
+fn mesh() -> Mesh {
+    Mesh {
+        hash: [7; 32],
+        normal: [0.0, 1.0, 0.5],
+        names: ["a".to_string(), "b".to_string()],
+        weights: [Some(1), None],
+    }
+}
+
+#[test]
+fn like_vec() {
+    // the primitive arrays and the lists are the same as for a Vec
+    assert_eq!(to_buffer(&[0.5_f32, 1.0]), to_buffer(&vec![0.5_f32, 1.0]));
+    assert_eq!(to_buffer(&[1_u8, 2]), to_buffer(&vec![1_u8, 2]));
+    assert_eq!(
+        to_buffer(&["a".to_string()]),
+        to_buffer(&vec!["a".to_string()])
+    );
+    let obj: [i32; 3] = from_buffer(&to_buffer(&vec![1, 2, 3])).expect("should success");
+    assert_eq!(obj, [1, 2, 3]);
+}
+
+#[test]
+fn round_trip() {
+    let value = mesh();
+    let bin = to_buffer(&value);
+    assert_eq!(serialized_size(&value), bin.len());
+    let obj: Mesh = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, value);
+
+    let config = Config {
+        native: true,
+        ..Default::default()
+    };
+    let bin = to_buffer_with_config(&value, &config);
+    let obj: Mesh = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, value);
+}
+
+#[test]
+fn length() {
+    let bin = to_buffer(&vec![1_i32, 2, 3]);
+    let err = from_buffer::<[i32; 2]>(&bin).expect_err("should fail");
+    assert!(matches!(
+        err,
+        Error::ArrayLength {
+            expected: 2,
+            actual: 3
+        }
+    ));
+}
+
+#[test]
+fn odd_bytes() {
+    let mut bin = to_buffer(&vec![1_i32, 2]);
+    // 7 bytes after the length, the last element is cut short
+    assert_eq!(bin[13], 8);
+    bin[13] = 7;
+    bin.pop();
+    let err = from_buffer::<Vec<i32>>(&bin).expect_err("should fail");
+    assert!(matches!(err, Error::ArrayBytes { len: 7, size: 4 }));
+}
@@ -0,0 +1,72 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use fury::__derive::{Reader, Writer};
+use fury::{from_buffer_with_config, to_buffer, to_buffer_with_config, Config, LongEncoding};
+use std::collections::HashMap;
+
+#[test]
+fn varint() {
+    let mut writer = Writer::default();
+    writer.var_uint32(300);
+    writer.var_int32(-1);
+    writer.var_int32(i32::MIN);
+    writer.var_uint64(u64::MAX);
+    writer.var_int64(-2);
+    writer.sli_int64(-1);
+    writer.sli_int64(1 << 40);
+    let bin = writer.dump();
+    assert_eq!(
+        bin,
+        [
+            [0xAC, 0x02].as_slice(),
+            &[0x01],
+            &[0xFF, 0xFF, 0xFF, 0xFF, 0x0F],
+            &[0xFF; 9],
+            &[0x03],
+            &(-2i32).to_le_bytes(),
+            &[0x01],
+            &(1i64 << 40).to_le_bytes(),
+        ]
+        .concat()
+    );
+
+    let mut reader = Reader::new(&bin);
+    assert_eq!(reader.var_uint32().unwrap(), 300);
+    assert_eq!(reader.var_int32().unwrap(), -1);
+    assert_eq!(reader.var_int32().unwrap(), i32::MIN);
+    assert_eq!(reader.var_uint64().unwrap(), u64::MAX);
+    assert_eq!(reader.var_int64().unwrap(), -2);
+    assert_eq!(reader.sli_int64().unwrap(), -1);
+    assert_eq!(reader.sli_int64().unwrap(), 1 << 40);
+    assert_eq!(reader.remaining(), 0);
+}
+
+#[test]
+fn compressed_numbers() {
+    let value = HashMap::from([(-1i32, i64::MIN), (1 << 20, -1)]);
+    for long_encoding in [LongEncoding::SLI, LongEncoding::PVL] {
+        let config = Config {
+            compress_int: true,
+            compress_long: true,
+            long_encoding,
+            ..Default::default()
+        };
+        let bin = to_buffer_with_config(&value, &config);
+        assert!(bin.len() < to_buffer(&value).len());
+        let obj: HashMap<i32, i64> =
+            from_buffer_with_config(&bin, &config).expect("should success");
+        assert_eq!(obj, value);
+    }
+}
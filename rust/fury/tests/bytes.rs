@@ -0,0 +1,59 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(feature = "bytes")]
+
+use bytes::{Bytes, BytesMut};
+use fury::{from_buffer, from_bytes, serialized_size, to_buf_mut, to_buffer, Error};
+use std::collections::HashMap;
+
+#[test]
+fn zero_copy() {
+    let value = vec![7u8; 100];
+    let bin = Bytes::from(to_buffer(&value));
+    let shared: Bytes = from_bytes(&bin).expect("should success");
+    assert_eq!(shared, value);
+    // the result points into the input instead of a copy
+    let range = bin.as_ptr_range();
+    assert!(range.contains(&shared.as_ptr()));
+
+    // and it is written exactly like Vec<u8>
+    assert_eq!(to_buffer(&shared), to_buffer(&value));
+    let copied: Bytes = from_buffer(&bin).expect("should success");
+    assert_eq!(copied, shared);
+}
+
+#[test]
+fn buf_mut() {
+    let value = HashMap::from([("hello".to_string(), vec![1u8, 2, 3])]);
+    let mut bf = BytesMut::from(&b"prefix"[..]);
+    to_buf_mut(&value, &mut bf).expect("should success");
+    assert_eq!(&bf[..6], b"prefix");
+    assert_eq!(&bf[6..], to_buffer(&value).as_slice());
+
+    let bin = bf.split_off(6).freeze();
+    let result: HashMap<String, Bytes> = from_bytes(&bin).expect("should success");
+    assert_eq!(result["hello"], Bytes::from_static(&[1, 2, 3]));
+}
+
+#[test]
+fn buf_mut_full() {
+    let value = vec!["hello".to_string(); 4];
+    let mut bf = [0u8; 16];
+    let result = to_buf_mut(&value, &mut &mut bf[..]);
+    assert!(matches!(
+        result,
+        Err(Error::BufferFull { needed, capacity: 16 }) if needed == serialized_size(&value)
+    ));
+}
@@ -0,0 +1,113 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use fury::{from_buffer, serialized_size, to_buffer, Fury, Value};
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, LinkedList, VecDeque};
+use std::hash::BuildHasherDefault;
+
+#[derive(Default)]
+struct IdentityHasher(u64);
+
+impl std::hash::Hasher for IdentityHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        bytes
+            .iter()
+            .for_each(|b| self.0 = (self.0 << 8) | *b as u64);
+    }
+}
+
+#[derive(Fury, Debug, PartialEq)]
+#[tag("example.inventory")]
+struct Inventory {
+    prices: BTreeMap<String, i64>,
+    tags: BTreeSet<String>,
+    queue: VecDeque<i32>,
+    history: LinkedList<String>,
+    ids: HashMap<i32, String, BuildHasherDefault<IdentityHasher>>,
+    seen: HashSet<i32, RandomState>,
+}
+
+#[test]
+fn round_trip() {
+    let value = Inventory {
+        prices: BTreeMap::from([("b".to_string(), 2), ("a".to_string(), 1)]),
+        tags: BTreeSet::from(["x".to_string(), "y".to_string()]),
+        queue: VecDeque::from([3, 1, 2]),
+        history: LinkedList::from(["first".to_string(), "second".to_string()]),
+        ids: [(1, "one".to_string())].into_iter().collect(),
+        seen: HashSet::from_iter([4, 5]),
+    };
+    let bin = to_buffer(&value);
+    assert_eq!(serialized_size(&value), bin.len());
+    let obj: Inventory = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, value);
+}
+
+#[test]
+fn same_as_std() {
+    // the maps and sets are written like HashMap and HashSet
+    let map = BTreeMap::from([(1, 2)]);
+    assert_eq!(to_buffer(&map), to_buffer(&HashMap::from([(1, 2)])));
+    let obj: HashMap<i32, i32> = from_buffer(&to_buffer(&map)).expect("should success");
+    assert_eq!(obj, HashMap::from([(1, 2)]));
+    let set = BTreeSet::from([1]);
+    assert_eq!(to_buffer(&set), to_buffer(&HashSet::from([1])));
+
+    // the lists can be read from the primitive array of a Vec and the other way around
+    let obj: VecDeque<i32> = from_buffer(&to_buffer(&vec![1, 2])).expect("should success");
+    assert_eq!(obj, VecDeque::from([1, 2]));
+    let obj: Vec<i32> = from_buffer(&to_buffer(&VecDeque::from([1, 2]))).expect("should success");
+    assert_eq!(obj, vec![1, 2]);
+}
+
+#[test]
+fn binary_heap() {
+    let value = BinaryHeap::from([3, 1, 2]);
+    let obj: BinaryHeap<i32> = from_buffer(&to_buffer(&value)).expect("should success");
+    assert_eq!(obj.into_sorted_vec(), vec![1, 2, 3]);
+}
+
+/// A map in the order a `LinkedHashMap` of Java may write it.
+fn linked_map() -> Vec<u8> {
+    to_buffer(&Value::Map(vec![
+        (Value::String("b".to_string()), Value::Int32(2)),
+        (Value::String("a".to_string()), Value::Int32(1)),
+    ]))
+}
+
+#[test]
+fn sorted_map() {
+    let obj: BTreeMap<String, i32> = from_buffer(&linked_map()).expect("should success");
+    assert_eq!(obj.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+}
+
+#[cfg(feature = "indexmap")]
+#[test]
+fn wire_order() {
+    use indexmap::{IndexMap, IndexSet};
+
+    let obj: IndexMap<String, i32> = from_buffer(&linked_map()).expect("should success");
+    assert_eq!(obj.keys().collect::<Vec<_>>(), vec!["b", "a"]);
+    let bin = to_buffer(&obj);
+    assert_eq!(bin, linked_map());
+
+    let set = IndexSet::from([3, 1, 2]);
+    let obj: IndexSet<i32> = from_buffer(&to_buffer(&set)).expect("should success");
+    assert_eq!(obj.into_iter().collect::<Vec<_>>(), vec![3, 1, 2]);
+}
@@ -0,0 +1,165 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use fury::{
+    from_buffer, from_buffer_with_config, serialized_size, serialized_size_with_config, to_buffer,
+    to_buffer_with_config, Config, Error, Fury,
+};
+use std::collections::HashMap;
+
+mod v1 {
+    use fury::Fury;
+
+    #[derive(Fury, Debug, Default, PartialEq)]
+    #[tag("example.item")]
+    #[compatible]
+    pub struct Item {
+        pub id: i32,
+        pub name: String,
+    }
+}
+
+mod v2 {
+    use fury::Fury;
+    use std::collections::HashMap;
+
+    #[derive(Fury, Debug, Default, PartialEq)]
+    #[tag("example.inner")]
+    #[compatible]
+    pub struct Inner {
+        pub flags: Vec<bool>,
+    }
+
+    #[derive(Fury, Debug, Default, PartialEq)]
+    #[tag("example.item")]
+    #[compatible]
+    pub struct Item {
+        pub id: i32,
+        pub inner: Inner,
+        pub labels: Vec<String>,
+        pub scores: HashMap<String, Vec<i64>>,
+        pub r#type: Option<i16>,
+    }
+}
+
+#[test]
+fn forward_and_backward() {
+    let old = v1::Item {
+        id: 7,
+        name: "seven".to_string(),
+    };
+    let new = v2::Item {
+        id: 8,
+        inner: v2::Inner {
+            flags: vec![true, false],
+        },
+        labels: vec!["a".to_string(), "b".to_string()],
+        scores: HashMap::from([("x".to_string(), vec![1, 2, 3])]),
+        r#type: Some(3),
+    };
+
+    // the new fields are taken from Default
+    let bin = to_buffer(&old);
+    assert_eq!(serialized_size(&old), bin.len());
+    let obj: v2::Item = from_buffer(&bin).expect("should success");
+    assert_eq!(
+        obj,
+        v2::Item {
+            id: 7,
+            ..Default::default()
+        }
+    );
+
+    // the unknown fields are skipped, the missing ones are taken from Default
+    let bin = to_buffer(&new);
+    assert_eq!(serialized_size(&new), bin.len());
+    let obj: v1::Item = from_buffer(&bin).expect("should success");
+    assert_eq!(
+        obj,
+        v1::Item {
+            id: 8,
+            name: String::new(),
+        }
+    );
+
+    let obj: v2::Item = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, new);
+}
+
+#[derive(Fury, Debug, Default, PartialEq)]
+#[tag("example.point")]
+struct Point {
+    x: i64,
+    y: i64,
+}
+
+#[derive(Fury, Debug, Default, PartialEq)]
+#[tag("example.point")]
+#[fury(default)]
+struct Point3 {
+    x: i64,
+    y: i64,
+    z: i64,
+}
+
+#[test]
+fn global_config() {
+    let config = Config {
+        compatible: true,
+        ..Default::default()
+    };
+    let value = Point3 { x: 1, y: 2, z: 3 };
+    let bin = to_buffer_with_config(&value, &config);
+    assert_eq!(serialized_size_with_config(&value, &config), bin.len());
+    assert_ne!(bin, to_buffer(&value));
+
+    // the layout is detected, the config is only needed for writing
+    let obj: Point = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, Point { x: 1, y: 2 });
+
+    // the missing field is taken from Default under the config
+    let bin = to_buffer_with_config(&Point { x: 1, y: 2 }, &config);
+    let obj: Point3 = from_buffer_with_config(&bin, &config).expect("should success");
+    assert_eq!(obj, Point3 { x: 1, y: 2, z: 0 });
+
+    // it is an error without the config
+    let obj: Result<Point3, Error> = from_buffer(&bin);
+    assert!(matches!(obj, Err(Error::MissingField("z"))));
+}
+
+#[test]
+fn skip_consistent_struct() {
+    #[derive(Fury, Debug, Default, PartialEq)]
+    #[tag("example.outer")]
+    #[compatible]
+    struct Outer {
+        id: i32,
+    }
+
+    #[derive(Fury, Debug, Default, PartialEq)]
+    #[tag("example.outer")]
+    #[compatible]
+    struct NewOuter {
+        id: i32,
+        point: Point,
+    }
+
+    // a struct in the hashed layout has no field names, so it can't be skipped
+    let bin = to_buffer(&NewOuter {
+        id: 1,
+        point: Point { x: 1, y: 2 },
+    });
+    let obj: Result<Outer, Error> = from_buffer(&bin);
+    assert!(matches!(obj, Err(Error::Skip(_))));
+}
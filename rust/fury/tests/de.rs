@@ -11,3 +11,220 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+
+use fury::{
+    from_buffer, from_buffer_with_config, from_reader, serialized_size,
+    serialized_size_with_config, to_buffer, to_buffer_with_config, Config, Error,
+};
+use std::collections::HashMap;
+use std::io::BufReader;
+
+#[test]
+fn truncated_buffer() {
+    let value = HashMap::from([
+        ("hello1".to_string(), vec![1i64, 2, 3]),
+        ("hello2".to_string(), vec![4i64, 5]),
+    ]);
+    let bin = to_buffer(&value);
+    assert_eq!(
+        from_buffer::<HashMap<String, Vec<i64>>>(&bin).expect("should success"),
+        value
+    );
+    for len in 0..bin.len() {
+        let result = from_buffer::<HashMap<String, Vec<i64>>>(&bin[..len]);
+        assert!(
+            matches!(result, Err(Error::UnexpectedEof { .. })),
+            "prefix of {len} bytes should fail with UnexpectedEof"
+        );
+    }
+}
+
+#[test]
+fn string_and_bool_arrays() {
+    // the elements of both arrays have no heads
+    let value = vec!["a".to_string(), "b".to_string()];
+    let obj: Vec<String> = from_buffer(&to_buffer(&value)).expect("should success");
+    assert_eq!(obj, value);
+    let value = vec![true, false, true];
+    let obj: Vec<bool> = from_buffer(&to_buffer(&value)).expect("should success");
+    assert_eq!(obj, value);
+}
+
+#[test]
+fn oversized_length() {
+    let mut bin = to_buffer(&vec![1i32, 2, 3]);
+    // the length of the array follows the 10 byte header and the ref and type flags
+    bin[13] = 0x7F;
+    assert!(matches!(
+        from_buffer::<Vec<i32>>(&bin),
+        Err(Error::UnexpectedEof {
+            needed: 127,
+            remaining: 12
+        })
+    ));
+}
+
+#[test]
+fn big_endian() {
+    let mut bin = to_buffer(&vec![1i32, 2, 3]);
+    assert_eq!(
+        from_buffer::<Vec<i32>>(&bin).expect("should success"),
+        vec![1, 2, 3]
+    );
+    // clear the little-endian flag of the header
+    bin[0] &= !2;
+    assert!(matches!(
+        from_buffer::<Vec<i32>>(&bin),
+        Err(Error::BigEndian)
+    ));
+}
+
+#[test]
+fn null_root() {
+    let bin = to_buffer(&None::<Vec<i32>>);
+    // java writes a null root as the bitmap only too
+    assert_eq!(bin, vec![1]);
+    assert_eq!(serialized_size(&None::<Vec<i32>>), 1);
+    assert_eq!(
+        from_buffer::<Option<Vec<i32>>>(&bin).expect("should success"),
+        None
+    );
+    assert!(matches!(from_buffer::<Vec<i32>>(&bin), Err(Error::Null)));
+    // pyfury writes the rest of the header and a null flag after the bitmap
+    let py = [7, 2, 11, 0, 0, 0, 0, 0, 0, 0, 253];
+    assert_eq!(
+        from_buffer::<Option<Vec<i32>>>(&py).expect("should success"),
+        None
+    );
+
+    let bin = to_buffer(&Some(vec![1i32]));
+    assert_eq!(
+        from_buffer::<Option<Vec<i32>>>(&bin).expect("should success"),
+        Some(vec![1])
+    );
+
+    let bin = [to_buffer(&None::<i32>), to_buffer(&Some(1i32))].concat();
+    let mut source = BufReader::new(bin.as_slice());
+    assert_eq!(
+        from_reader::<Option<i32>, _>(&mut source).expect("should success"),
+        None
+    );
+    assert_eq!(
+        from_reader::<Option<i32>, _>(&mut source).expect("should success"),
+        Some(1)
+    );
+}
+
+#[test]
+fn header() {
+    let bin = to_buffer(&1i32);
+    assert_eq!(&bin[..2], &[2 | 4, 0]);
+
+    // java and pyfury write their own language
+    let mut java = bin.clone();
+    java[1] = 1;
+    assert_eq!(from_buffer::<i32>(&java).expect("should success"), 1);
+    let mut unknown = bin.clone();
+    unknown[1] = 100;
+    assert!(matches!(
+        from_buffer::<i32>(&unknown),
+        Err(Error::UnsupportLanguageCode { code: 100 })
+    ));
+
+    // a payload of the java or python native mode
+    let mut native = bin.clone();
+    native[0] &= !4;
+    assert!(matches!(
+        from_buffer::<i32>(&native),
+        Err(Error::NotCrossLanguage)
+    ));
+
+    // a payload with out-of-band buffers
+    let mut out_of_band = bin.clone();
+    out_of_band[0] |= 8;
+    assert!(matches!(
+        from_buffer::<i32>(&out_of_band),
+        Err(Error::OutOfBand)
+    ));
+
+    // a newer version of the header
+    let mut newer = bin.clone();
+    newer[0] |= 1 << 4;
+    assert!(matches!(
+        from_buffer::<i32>(&newer),
+        Err(Error::HeaderVersion(1))
+    ));
+    assert!(matches!(
+        from_buffer::<Option<i32>>(&[1 | 2 << 4]),
+        Err(Error::HeaderVersion(2))
+    ));
+}
+
+#[test]
+fn incremental() {
+    let first = vec![HashMap::from([("hello".to_string(), 1i64)]); 100];
+    let second = HashMap::from([(1i32, 2.0f64)]);
+    let mut bin = to_buffer(&first);
+    bin.extend(to_buffer(&second));
+
+    // a tiny buffer forces the reader to refill many times
+    let mut source = BufReader::with_capacity(3, bin.as_slice());
+    let obj: Vec<HashMap<String, i64>> = from_reader(&mut source).expect("should success");
+    assert_eq!(obj, first);
+    let obj: HashMap<i32, f64> = from_reader(&mut source).expect("should success");
+    assert_eq!(obj, second);
+
+    let bin = to_buffer(&first);
+    let result = from_reader::<Vec<HashMap<String, i64>>, _>(&bin[..bin.len() - 5]);
+    assert!(matches!(result, Err(Error::UnexpectedEof { .. })));
+}
+
+#[test]
+fn string_encodings() {
+    let config = Config {
+        compress_string: true,
+        ..Default::default()
+    };
+    // the coder, length and bytes java writes for each of them
+    let cases = [
+        ("hello", vec![0, 5, b'h', b'e', b'l', b'l', b'o']),
+        ("caf\u{e9}", vec![0, 4, b'c', b'a', b'f', 0xe9]),
+        ("\u{4f60}\u{597d}", vec![1, 4, 0x60, 0x4f, 0x7d, 0x59]),
+        (
+            "a\u{4f60}\u{597d}",
+            vec![1, 6, b'a', 0, 0x60, 0x4f, 0x7d, 0x59],
+        ),
+        (
+            "\u{e9}\u{1f600}",
+            vec![2, 6, 0xc3, 0xa9, 0xf0, 0x9f, 0x98, 0x80],
+        ),
+    ];
+    for (value, expected) in cases {
+        let value = value.to_string();
+        let bin = to_buffer_with_config(&value, &config);
+        assert_eq!(serialized_size_with_config(&value, &config), bin.len());
+        assert_eq!(&bin[13..], expected.as_slice());
+        let obj: String = from_buffer_with_config(&bin, &config).expect("should success");
+        assert_eq!(obj, value);
+    }
+
+    let value = vec!["caf\u{e9}".to_string(), "\u{4f60}".to_string()];
+    let bin = to_buffer_with_config(&value, &config);
+    let obj: Vec<String> = from_buffer_with_config(&bin, &config).expect("should success");
+    assert_eq!(obj, value);
+
+    // a lone surrogate
+    let mut bin = to_buffer_with_config(&"\u{4f60}".to_string(), &config);
+    bin[15..].copy_from_slice(&[0x00, 0xd8]);
+    let obj: Result<String, Error> = from_buffer_with_config(&bin, &config);
+    assert!(matches!(obj, Err(Error::Utf16)));
+}
+
+#[test]
+fn invalid_utf8() {
+    let mut bin = to_buffer(&"ab".to_string());
+    let len = bin.len();
+    bin[len - 1] = 0xff;
+    let obj: Result<String, Error> = from_buffer(&bin);
+    assert!(matches!(obj, Err(Error::Utf8(_))));
+}
@@ -0,0 +1,145 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use fury::{from_buffer, to_buffer, to_buffer_with_config, Config, Error, Fury};
+
+#[derive(Fury, Debug, PartialEq)]
+#[tag("example.color")]
+enum Color {
+    Red,
+    Green,
+    Blue,
+}
+
+#[derive(Fury, Debug, PartialEq)]
+#[tag("example.color")]
+#[by_name]
+enum NamedColor {
+    Red,
+    Green,
+    Blue,
+}
+
+#[derive(Fury, Debug, PartialEq)]
+#[tag("example.shape")]
+enum Outline {
+    Empty,
+    Circle(f32),
+}
+
+#[derive(Fury, Debug, PartialEq)]
+#[tag("example.pick")]
+struct Pick {
+    color: Color,
+}
+
+#[derive(Fury, Debug, PartialEq)]
+#[tag("example.shape")]
+enum Shape {
+    Empty,
+    Circle(f64),
+    Rect { width: i32, height: i32 },
+    Polygon(Vec<i32>, Option<String>),
+}
+
+#[derive(Fury, Debug, PartialEq)]
+#[tag("example.canvas")]
+struct Canvas {
+    background: Color,
+    shapes: Vec<Shape>,
+}
+
+fn canvas() -> Canvas {
+    Canvas {
+        background: Color::Green,
+        shapes: vec![
+            Shape::Empty,
+            Shape::Circle(1.5),
+            Shape::Rect {
+                width: 3,
+                height: 4,
+            },
+            Shape::Polygon(vec![1, 2, 3], None),
+        ],
+    }
+}
+
+#[test]
+fn ordinal() {
+    let bin = to_buffer(&Color::Blue);
+    // the enum type id and the ordinal after the header and the ref flag
+    assert_eq!(&bin[11..], &[13, 1, 2]);
+    let obj: Color = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, Color::Blue);
+}
+
+#[test]
+fn by_name() {
+    let bin = to_buffer(&NamedColor::Green);
+    assert!(bin.ends_with(&[5, b'G', b'r', b'e', b'e', b'n']));
+    let obj: NamedColor = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, NamedColor::Green);
+}
+
+#[test]
+fn data_carrying() {
+    let value = canvas();
+    let bin = to_buffer(&value);
+    let obj: Canvas = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, value);
+}
+
+#[test]
+fn native() {
+    let config = Config {
+        native: true,
+        ..Default::default()
+    };
+    let value = canvas();
+    let bin = to_buffer_with_config(&value, &config);
+    let obj: Canvas = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, value);
+}
+
+#[test]
+fn java() {
+    // written by java for:
+    // enum Color { Red, Green, Blue }
+    // class Pick { Color color; } registered as "example.pick"
+    let bin = [
+        6, 1, 44, 0, 0, 0, 0, 0, 0, 0, 255, 0, 1, 0, 74, 200, 55, 231, 1, 144, 207, 9, 12, 0, 101,
+        120, 97, 109, 112, 108, 101, 46, 112, 105, 99, 107, 28, 3, 0, 0, 255, 13, 1, 2,
+    ];
+    let value = Pick { color: Color::Blue };
+    let obj: Pick = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, value);
+    // the same bytes after the header
+    assert_eq!(&to_buffer(&value)[10..], &bin[10..]);
+}
+
+#[test]
+fn hash_mismatch() {
+    // the same tag with other variants
+    let bin = to_buffer(&Shape::Empty);
+    let err = from_buffer::<Outline>(&bin).expect_err("should fail");
+    assert!(matches!(err, Error::StructHash { .. }));
+}
+
+#[test]
+fn unknown_variant() {
+    let mut bin = to_buffer(&Color::Red);
+    *bin.last_mut().unwrap() = 7;
+    let err = from_buffer::<Color>(&bin).expect_err("should fail");
+    assert!(matches!(err, Error::UnknownVariant(variant) if variant == "7"));
+}
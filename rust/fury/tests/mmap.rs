@@ -0,0 +1,67 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(feature = "mmap")]
+
+use fury::mmap::MappedFile;
+use fury::{from_reader, to_buffer, to_row, FuryRow};
+use std::collections::HashMap;
+use std::fs;
+
+#[derive(FuryRow)]
+struct Foo {
+    f1: String,
+    f2: Vec<i32>,
+}
+
+fn temp_file(name: &str, data: &[u8]) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("fury-{}-{name}", std::process::id()));
+    fs::write(&path, data).expect("should write");
+    path
+}
+
+#[test]
+fn mapped_row() {
+    let path = temp_file(
+        "row",
+        &to_row(&Foo {
+            f1: "hello".to_string(),
+            f2: vec![1, 2, 3],
+        }),
+    );
+    let file = unsafe { MappedFile::open(&path) }.expect("should map");
+    // the viewer outlives the call which opened the file
+    let foo = file.row::<Foo>();
+    assert_eq!(foo.f1(), "hello");
+    assert_eq!(foo.f2().get(2), 3);
+    fs::remove_file(path).expect("should remove");
+}
+
+#[test]
+fn mapped_buffer() {
+    let value = HashMap::from([("hello".to_string(), vec![1i64, 2, 3])]);
+    let path = temp_file("buffer", &to_buffer(&value));
+    let file = unsafe { MappedFile::open(&path) }.expect("should map");
+    assert_eq!(
+        file.deserialize::<HashMap<String, Vec<i64>>>()
+            .expect("should success"),
+        value
+    );
+    assert_eq!(&*file, to_buffer(&value).as_slice());
+    let mut reader = file.reader();
+    assert_eq!(reader.u8().expect("should read head"), to_buffer(&value)[0]);
+    let result: HashMap<String, Vec<i64>> = from_reader(file.as_slice()).expect("should success");
+    assert_eq!(result, value);
+    fs::remove_file(path).expect("should remove");
+}
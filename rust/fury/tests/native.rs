@@ -0,0 +1,144 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use fury::{
+    from_buffer, from_buffer_with_config, serialized_size_with_config, to_buffer,
+    to_buffer_with_config, Config, Error, Fury,
+};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+fn native() -> Config {
+    Config {
+        native: true,
+        ..Default::default()
+    }
+}
+
+#[derive(Fury, Debug, PartialEq)]
+#[tag("example.job")]
+struct Job {
+    id: usize,
+    name: String,
+    retries: Option<i32>,
+    timeout: Option<Option<i64>>,
+    outcome: Result<Vec<i32>, String>,
+    labels: HashMap<String, String>,
+}
+
+fn job() -> Job {
+    Job {
+        id: 42,
+        name: "build".to_string(),
+        retries: None,
+        timeout: Some(None),
+        outcome: Ok(vec![1, 2, 3]),
+        labels: HashMap::from([("a".to_string(), "b".to_string())]),
+    }
+}
+
+#[test]
+fn round_trip() {
+    let value = job();
+    let bin = to_buffer_with_config(&value, &native());
+    assert_eq!(serialized_size_with_config(&value, &native()), bin.len());
+    // the language of the head is rust
+    assert_eq!(bin[1], 6);
+    // the head selects the mode, the config of the reader doesn't
+    let obj: Job = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, value);
+
+    // the fields and the root have no ref flags and type ids
+    assert!(bin.len() < to_buffer(&value).len());
+
+    let value = Job {
+        timeout: Some(Some(10)),
+        outcome: Err("failed".to_string()),
+        ..job()
+    };
+    let obj: Job = from_buffer(&to_buffer_with_config(&value, &native())).expect("should success");
+    assert_eq!(obj, value);
+}
+
+#[test]
+fn nested_option() {
+    for value in [None, Some(None), Some(Some(1i32))] {
+        let bin = to_buffer_with_config(&value, &native());
+        assert_eq!(serialized_size_with_config(&value, &native()), bin.len());
+        let obj: Option<Option<i32>> = from_buffer(&bin).expect("should success");
+        assert_eq!(obj, value);
+    }
+    // the cross-language payloads can't tell Some(None) from None
+    let obj: Option<Option<i32>> =
+        from_buffer(&to_buffer(&Some(None::<i32>))).expect("should success");
+    assert_eq!(obj, None);
+}
+
+#[test]
+fn rust_types_in_xlang() {
+    let value = job();
+    let obj: Job = from_buffer(&to_buffer(&value)).expect("should success");
+    assert_eq!(
+        obj,
+        Job {
+            timeout: None,
+            ..value
+        }
+    );
+
+    let mut bin = to_buffer(&Ok::<i32, String>(1));
+    let len = bin.len();
+    // the flag of the result follows the head and the ref and type flags
+    bin[len - 8] = 2;
+    assert!(matches!(
+        from_buffer::<Result<i32, String>>(&bin),
+        Err(Error::ResultFlag(2))
+    ));
+
+    let bin = to_buffer(&u64::MAX);
+    let obj: Result<usize, Error> = from_buffer(&bin);
+    if usize::BITS < 64 {
+        assert!(matches!(obj, Err(Error::Usize(u64::MAX))));
+    } else {
+        assert_eq!(obj.expect("should success"), usize::MAX);
+    }
+}
+
+#[test]
+fn cyclic() {
+    #[derive(Fury, Debug, Default)]
+    #[tag("example.native_node")]
+    struct Node {
+        value: i32,
+        parent: Weak<RefCell<Node>>,
+        children: Vec<Rc<RefCell<Node>>>,
+    }
+
+    let root = Rc::new(RefCell::new(Node::default()));
+    let child = Rc::new(RefCell::new(Node {
+        value: 1,
+        parent: Rc::downgrade(&root),
+        ..Default::default()
+    }));
+    root.borrow_mut().children.push(child);
+
+    // shared objects keep their ref flags in the native mode
+    let bin = to_buffer_with_config(&root, &native());
+    let obj: Rc<RefCell<Node>> =
+        from_buffer_with_config(&bin, &Config::default()).expect("should success");
+    let parent = obj.borrow().children[0].borrow().parent.upgrade().unwrap();
+    assert!(Rc::ptr_eq(&parent, &obj));
+    assert_eq!(obj.borrow().children[0].borrow().value, 1);
+}
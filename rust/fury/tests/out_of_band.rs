@@ -0,0 +1,91 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use fury::{
+    from_buffer, from_buffer_with_buffers, to_buffer, to_buffer_with_callback, Error, Fury,
+};
+
+#[derive(Fury, Debug, PartialEq)]
+#[tag("example.tensor")]
+struct Tensor {
+    name: String,
+    shape: Vec<i64>,
+    data: Vec<f64>,
+    mask: Vec<bool>,
+    raw: Vec<u8>,
+}
+
+fn tensor() -> Tensor {
+    Tensor {
+        name: "t".to_string(),
+        shape: vec![2, 3],
+        data: (0..6).map(|i| i as f64).collect(),
+        mask: vec![true, false, true],
+        raw: vec![7; 100],
+    }
+}
+
+#[test]
+fn all_out_of_band() {
+    let value = tensor();
+    let mut buffers = Vec::new();
+    let bin = to_buffer_with_callback(&value, |buffer| {
+        buffers.push(buffer.to_buffer());
+        false
+    });
+    // the fields are sorted by name: data, mask, name, raw, shape
+    assert_eq!(buffers.len(), 4);
+    assert_eq!(
+        buffers[0],
+        (0..6)
+            .flat_map(|i| (i as f64).to_le_bytes())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(buffers[1], vec![1, 0, 1]);
+    assert_eq!(buffers[2], vec![7; 100]);
+    assert_eq!(
+        buffers[3],
+        [2i64.to_le_bytes(), 3i64.to_le_bytes()].concat()
+    );
+    // the out-of-band flag of the header
+    assert_eq!(bin[0] & 8, 8);
+
+    let buffers: Vec<&[u8]> = buffers.iter().map(Vec::as_slice).collect();
+    let obj: Tensor = from_buffer_with_buffers(&bin, &buffers).expect("should success");
+    assert_eq!(obj, value);
+
+    // the buffers are needed, and all of them
+    assert!(matches!(from_buffer::<Tensor>(&bin), Err(Error::OutOfBand)));
+    assert!(matches!(
+        from_buffer_with_buffers::<Tensor>(&bin, &buffers[..3]),
+        Err(Error::BufferMissing)
+    ));
+}
+
+#[test]
+fn in_band() {
+    let value = tensor();
+    // the callback keeps every buffer in band, only the flags are added
+    let bin = to_buffer_with_callback(&value, |_| true);
+    assert_eq!(bin.len(), to_buffer(&value).len() + 4);
+    let obj: Tensor = from_buffer_with_buffers(&bin, &[]).expect("should success");
+    assert_eq!(obj, value);
+
+    // a payload without out-of-band buffers can't be read with them
+    let bin = to_buffer(&value);
+    assert!(matches!(
+        from_buffer_with_buffers::<Tensor>(&bin, &[]),
+        Err(Error::OutOfBand)
+    ));
+}
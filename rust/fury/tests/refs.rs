@@ -0,0 +1,173 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use fury::{from_buffer, serialized_size, to_buffer, Error, Fury};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+use std::sync::Arc;
+
+#[test]
+fn shared_rc() {
+    let a = Rc::new("hello".to_string());
+    let b = Rc::new("hello".to_string());
+    let value = vec![a.clone(), b.clone(), a.clone(), b];
+    let bin = to_buffer(&value);
+    assert_eq!(serialized_size(&value), bin.len());
+
+    let obj: Vec<Rc<String>> = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, value);
+    assert!(Rc::ptr_eq(&obj[0], &obj[2]));
+    assert!(Rc::ptr_eq(&obj[1], &obj[3]));
+    assert!(!Rc::ptr_eq(&obj[0], &obj[1]));
+
+    // the repeated ones are only the ref flag and the ref id
+    assert!(bin.ends_with(&[-2i8 as u8, 0, -2i8 as u8, 1]));
+}
+
+#[test]
+fn shared_arc() {
+    let shared = Arc::new(HashMap::from([(1i32, vec![1i64, 2])]));
+    let value = HashMap::from([
+        ("a".to_string(), Some(shared.clone())),
+        ("b".to_string(), Some(shared)),
+        ("c".to_string(), None),
+    ]);
+    let obj: HashMap<String, Option<Arc<_>>> =
+        from_buffer(&to_buffer(&value)).expect("should success");
+    assert_eq!(obj, value);
+    let (a, b) = (obj["a"].as_ref().unwrap(), obj["b"].as_ref().unwrap());
+    assert!(Arc::ptr_eq(a, b));
+}
+
+#[test]
+fn many_shared() {
+    // more objects than the ref table keeps inline
+    let shared: Vec<_> = (0..20).map(|i| Rc::new(i.to_string())).collect();
+    let value: Vec<_> = shared.iter().chain(shared.iter()).cloned().collect();
+    let bin = to_buffer(&value);
+    assert_eq!(serialized_size(&value), bin.len());
+    let obj: Vec<Rc<String>> = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, value);
+    for i in 0..20 {
+        assert!(Rc::ptr_eq(&obj[i], &obj[i + 20]));
+    }
+}
+
+#[test]
+fn shared_null() {
+    let value = Rc::new(None::<i32>);
+    let bin = to_buffer(&value);
+    assert_eq!(serialized_size(&value), bin.len());
+    let obj: Rc<Option<i32>> = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, value);
+
+    let value = vec![Arc::new(None::<String>), Arc::new(Some("a".to_string()))];
+    let bin = to_buffer(&value);
+    assert_eq!(serialized_size(&value), bin.len());
+    let obj: Vec<Arc<Option<String>>> = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, value);
+}
+
+#[test]
+fn ref_value_of_plain_type() {
+    // payloads from writers which track every object flag plain values as RefValue too
+    let mut bin = to_buffer(&vec![Rc::new(1.0f64)]);
+    // the root vec takes ref id 0, so the element takes ref id 1
+    bin[10] = 0;
+    bin[13] = 2;
+    bin.extend([-2i8 as u8, 1]);
+    let obj: Vec<Rc<f64>> = from_buffer(&bin).expect("should success");
+    assert!(Rc::ptr_eq(&obj[0], &obj[1]));
+
+    // the vec isn't shared on this side, so a ref to it can't be resolved
+    let len = bin.len();
+    bin[len - 1] = 0;
+    assert!(matches!(
+        from_buffer::<Vec<Rc<f64>>>(&bin),
+        Err(Error::RefId(0))
+    ));
+}
+
+#[derive(Fury, Default)]
+#[tag("example.node")]
+struct Node {
+    value: i32,
+    parent: Weak<RefCell<Node>>,
+    children: Vec<Rc<RefCell<Node>>>,
+}
+
+#[test]
+fn cycle() {
+    let root = Rc::new(RefCell::new(Node::default()));
+    for value in 1..3 {
+        let child = Node {
+            value,
+            parent: Rc::downgrade(&root),
+            ..Default::default()
+        };
+        root.borrow_mut()
+            .children
+            .push(Rc::new(RefCell::new(child)));
+    }
+    let bin = to_buffer(&root);
+    assert_eq!(serialized_size(&root), bin.len());
+
+    let obj: Rc<RefCell<Node>> = from_buffer(&bin).expect("should success");
+    let children = &obj.borrow().children;
+    assert_eq!(children.len(), 2);
+    for (i, child) in children.iter().enumerate() {
+        assert_eq!(child.borrow().value, i as i32 + 1);
+        let parent = child.borrow().parent.upgrade().expect("should be alive");
+        assert!(Rc::ptr_eq(&parent, &obj));
+    }
+    assert!(obj.borrow().parent.upgrade().is_none());
+}
+
+#[test]
+fn untracked_weak() {
+    // written without a ref id, nothing would own the object
+    let bin = to_buffer(&7);
+    let err = from_buffer::<Weak<i32>>(&bin).expect_err("should fail");
+    assert!(matches!(err, Error::WeakUntracked));
+    let obj: Weak<i32> = from_buffer(&to_buffer(&Weak::<i32>::new())).expect("should success");
+    assert!(obj.upgrade().is_none());
+}
+
+#[test]
+fn cycle_tracking_every_object() {
+    // written by pyfury with `Fury(language=Language.XLANG, ref_tracking=True)` for
+    //
+    //     class Node:
+    //         children: List["Node"]
+    //         parent: "Node"
+    //         value: pyfury.Int32Type
+    //
+    // registered as "example.node", a root of value 1 holding one child of value 2 whose
+    // parent is the root. pyfury tracks the lists too, so they take ref ids of their own.
+    let bin = [
+        6, 2, 75, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 32, 241, 70, 128, 163, 34, 51, 170, 12, 0, 101,
+        120, 97, 109, 112, 108, 101, 46, 110, 111, 100, 101, 133, 101, 191, 43, 0, 25, 0, 1, 0, 0,
+        1, 1, 0, 0, 133, 101, 191, 43, 0, 25, 0, 0, 254, 0, 255, 7, 0, 2, 0, 0, 0, 253, 255, 7, 0,
+        1, 0, 0, 0,
+    ];
+
+    let obj: Rc<RefCell<Node>> = from_buffer(&bin).expect("should success");
+    assert_eq!(obj.borrow().value, 1);
+    let child = obj.borrow().children[0].clone();
+    assert_eq!(child.borrow().value, 2);
+    assert!(child.borrow().children.is_empty());
+    let parent = child.borrow().parent.upgrade().expect("should be alive");
+    assert!(Rc::ptr_eq(&parent, &obj));
+}
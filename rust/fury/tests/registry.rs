@@ -0,0 +1,167 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use fury::{
+    from_buffer, from_buffer_with_config, impl_polymorphic, serialized_size_with_config, to_buffer,
+    to_buffer_with_config, Config, Error, Fury, Polymorphic,
+};
+use std::rc::Rc;
+
+trait Event: Polymorphic {
+    fn describe(&self) -> String;
+}
+impl_polymorphic!(Event);
+
+#[derive(Fury, Debug, PartialEq)]
+#[tag("example.click")]
+struct Click {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Fury, Debug, PartialEq)]
+#[tag("example.key")]
+struct Key {
+    code: String,
+}
+
+impl Event for Click {
+    fn describe(&self) -> String {
+        format!("click {} {}", self.x, self.y)
+    }
+}
+
+impl Event for Key {
+    fn describe(&self) -> String {
+        format!("key {}", self.code)
+    }
+}
+
+#[derive(Fury)]
+#[tag("example.envelope")]
+struct Envelope {
+    events: Vec<Box<dyn Event>>,
+    last: Option<Box<dyn Event>>,
+    extra: Box<dyn Polymorphic>,
+}
+
+fn config() -> Config {
+    let mut config = Config::default();
+    config
+        .registry
+        .register_as::<dyn Event, Click>(|v| Box::new(v))
+        .register_as::<dyn Event, Key>(|v| Box::new(v))
+        .register::<Key>();
+    config
+}
+
+#[test]
+fn heterogeneous_list() {
+    let value = Envelope {
+        events: vec![
+            Box::new(Click { x: 1, y: 2 }),
+            Box::new(Key {
+                code: "a".to_string(),
+            }),
+            Box::new(Click { x: 3, y: 4 }),
+        ],
+        last: None,
+        extra: Box::new(Key {
+            code: "b".to_string(),
+        }),
+    };
+    let bin = to_buffer_with_config(&value, &config());
+    assert_eq!(serialized_size_with_config(&value, &config()), bin.len());
+
+    let obj: Envelope = from_buffer_with_config(&bin, &config()).expect("should success");
+    let events: Vec<String> = obj.events.iter().map(|e| e.describe()).collect();
+    assert_eq!(events, ["click 1 2", "key a", "click 3 4"]);
+    assert!(obj.last.is_none());
+    assert_eq!(
+        obj.extra.downcast_ref::<Key>(),
+        Some(&Key {
+            code: "b".to_string()
+        })
+    );
+    let extra = obj.extra.downcast::<Click>().expect_err("should be a Key");
+    assert!(extra.downcast::<Key>().is_ok());
+}
+
+#[test]
+fn written_as_the_struct() {
+    // java reads it as the class registered with the tag
+    let value: Box<dyn Event> = Box::new(Click { x: 1, y: 2 });
+    let bin = to_buffer(&value);
+    assert_eq!(bin, to_buffer(&Click { x: 1, y: 2 }));
+    let obj: Click = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, Click { x: 1, y: 2 });
+}
+
+#[test]
+fn unregistered() {
+    let bin = to_buffer(&Click { x: 1, y: 2 });
+    let obj: Result<Box<dyn Event>, Error> = from_buffer(&bin);
+    assert!(matches!(obj, Err(Error::UnregisteredTag(tag)) if tag == "example.click"));
+
+    // only Key is registered for Box<dyn Polymorphic>
+    let obj: Result<Box<dyn Polymorphic>, Error> = from_buffer_with_config(&bin, &config());
+    assert!(matches!(obj, Err(Error::UnregisteredTag(_))));
+}
+
+#[test]
+fn negative_tag_length() {
+    let mut bin = to_buffer(&Click { x: 1, y: 2 });
+    // header, ref flag, type id, tag flag and tag hash before the length
+    bin[22..24].copy_from_slice(&(-1i16).to_le_bytes());
+    let obj: Result<Box<dyn Event>, Error> = from_buffer_with_config(&bin, &config());
+    assert!(matches!(obj, Err(Error::TagLength(-1))));
+    let obj: Result<Click, Error> = from_buffer(&bin);
+    assert!(matches!(obj, Err(Error::TagLength(-1))));
+}
+
+#[test]
+fn untagged() {
+    // any type can be written, only the types with a tag can be told apart when read
+    let value: Box<dyn Polymorphic> = Box::new("a".to_string());
+    let bin = to_buffer(&value);
+    assert_eq!(bin, to_buffer(&"a".to_string()));
+    let obj: Result<Box<dyn Polymorphic>, Error> = from_buffer_with_config(&bin, &config());
+    assert!(matches!(obj, Err(Error::FieldType { .. })));
+}
+
+#[test]
+fn shared() {
+    let click: Rc<Box<dyn Event>> = Rc::new(Box::new(Click { x: 1, y: 2 }));
+    let value = vec![click.clone(), click];
+    let bin = to_buffer_with_config(&value, &config());
+    assert_eq!(serialized_size_with_config(&value, &config()), bin.len());
+
+    let obj: Vec<Rc<Box<dyn Event>>> =
+        from_buffer_with_config(&bin, &config()).expect("should success");
+    assert_eq!(obj[0].describe(), "click 1 2");
+    assert!(Rc::ptr_eq(&obj[0], &obj[1]));
+    assert!(obj[0].downcast_ref::<Key>().is_none());
+
+    let key: Rc<Box<dyn Polymorphic>> = Rc::new(Box::new(Key {
+        code: "a".to_string(),
+    }));
+    let obj: Rc<Box<dyn Polymorphic>> =
+        from_buffer_with_config(&to_buffer(&key), &config()).expect("should success");
+    assert_eq!(
+        obj.downcast_ref::<Key>(),
+        Some(&Key {
+            code: "a".to_string()
+        })
+    );
+}
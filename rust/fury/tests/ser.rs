@@ -0,0 +1,182 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use fury::__derive::{FieldType, FuryMeta, Serialize, SerializerState, Writer};
+use fury::{
+    serialized_size, serialized_size_with_config, to_buffer, to_buffer_with_callback,
+    to_buffer_with_config, to_writer, Config, Error, LongEncoding,
+};
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+/// A sink which remembers how the payload was handed over.
+#[derive(Default)]
+struct ChunkSink {
+    data: Vec<u8>,
+    chunks: usize,
+}
+
+impl io::Write for ChunkSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.data.extend_from_slice(buf);
+        self.chunks += 1;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn streaming() {
+    let value: Vec<Vec<String>> = (0..1000)
+        .map(|i| (0..100).map(|j| format!("{i}-{j}")).collect())
+        .collect();
+    let mut sink = ChunkSink::default();
+    to_writer(&value, &mut sink).expect("should success");
+    assert_eq!(sink.data, to_buffer(&value));
+    assert!(sink.chunks > 1);
+
+    let value = vec![vec![7u8; 1 << 20]];
+    let mut sink = ChunkSink::default();
+    to_writer(&value, &mut sink).expect("should success");
+    assert_eq!(sink.data, to_buffer(&value));
+}
+
+#[test]
+fn streaming_error() {
+    struct BrokenSink;
+
+    impl io::Write for BrokenSink {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let result = to_writer(&"hello".to_string(), BrokenSink);
+    assert!(matches!(result, Err(Error::Io(e)) if e.kind() == io::ErrorKind::BrokenPipe));
+}
+
+#[test]
+fn set_flushed_bytes() {
+    let mut sink = ChunkSink::default();
+    let mut writer = Writer::with_sink(&mut sink);
+    writer.skip(4);
+    writer.bytes(&[1; 1 << 17]);
+    writer.set_bytes(0, &[1, 2, 3, 4]);
+    assert!(matches!(
+        writer.flush(),
+        Err(Error::Flushed {
+            offset: 0,
+            flushed: 131076
+        })
+    ));
+}
+
+#[test]
+fn little_endian() {
+    let bin = to_buffer(&0x0102u16);
+    // the type id and the value follow the header and the ref flag
+    assert_eq!(&bin[11..], &[4, 0, 0x02, 0x01]);
+
+    // the length of a primitive array is the number of bytes, like java and pyfury write
+    let bin = to_buffer(&vec![0x0102i16, 0x0304]);
+    assert_eq!(&bin[13..], &[4, 0x02, 0x01, 0x04, 0x03]);
+
+    let bin = to_buffer(&vec![1.0f32]);
+    assert_eq!(&bin[13..], &[4, 0x00, 0x00, 0x80, 0x3f]);
+}
+
+#[test]
+fn exact_size() {
+    fn check<T: fury::__derive::Serialize>(value: &T) {
+        let configs = [
+            Config::default(),
+            Config {
+                compress_int: true,
+                compress_long: true,
+                long_encoding: LongEncoding::SLI,
+                ..Default::default()
+            },
+            Config {
+                compress_int: true,
+                compress_long: true,
+                long_encoding: LongEncoding::PVL,
+                ..Default::default()
+            },
+        ];
+        for config in configs.iter() {
+            assert_eq!(
+                serialized_size_with_config(value, config),
+                to_buffer_with_config(value, config).len()
+            );
+        }
+    }
+    for v in [0, 1, -1, 127, 128, 1 << 20, -(1 << 30), i32::MAX, i32::MIN] {
+        check(&v);
+    }
+    for v in [0, 1, -1, 1 << 30, 1 << 40, 1 << 56, i64::MAX, i64::MIN] {
+        check(&v);
+    }
+    check(&"x".repeat(200));
+    check(&vec![1u8; 300]);
+    check(&vec![vec![1i64, 2], vec![]]);
+    check(&vec![true, false]);
+    check(&vec![Some(1i32), None]);
+    check(&HashMap::from([
+        ("a".to_string(), vec![1.0f64]),
+        ("b".to_string(), vec![]),
+    ]));
+    check(&HashSet::from([1u16, 2, 3]));
+}
+
+/// A type of another crate, which only writes itself.
+struct Blob(Vec<u8>);
+
+impl FuryMeta for Blob {
+    fn ty() -> FieldType {
+        FieldType::BINARY
+    }
+}
+
+impl Serialize for Blob {
+    fn reserved_space() -> usize {
+        0
+    }
+
+    fn write(&self, serializer: &mut SerializerState) {
+        serializer.write_buffer(&self.0);
+    }
+}
+
+#[test]
+fn default_size() {
+    let value = vec![Blob(vec![1; 10]), Blob(vec![2; 300])];
+    let bin = to_buffer(&value);
+    assert_eq!(serialized_size(&value), bin.len());
+    assert_eq!(bin, to_buffer(&vec![vec![1u8; 10], vec![2u8; 300]]));
+
+    // the size is counted without handing the buffers to the callback
+    let mut calls = 0;
+    to_buffer_with_callback(&value, |_| {
+        calls += 1;
+        false
+    });
+    assert_eq!(calls, 2);
+}
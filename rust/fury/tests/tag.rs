@@ -0,0 +1,88 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use fury::__derive::compute_tag_hash;
+use fury::{from_buffer, serialized_size, to_buffer, Error, Fury, Value};
+
+#[derive(Fury, Debug, PartialEq)]
+#[tag("example.animal")]
+struct Animal {
+    name: String,
+}
+
+#[test]
+fn tag_hash() {
+    // the hashes written by java and pyfury, the first half of murmurhash3_x64_128 with seed 47
+    assert_eq!(compute_tag_hash("xxx"), 0xbaa4004a94f380f8u64 as i64);
+    assert_eq!(compute_tag_hash("aaaaaaaaa"), 0x1df66714aa1adf74);
+    assert_eq!(
+        compute_tag_hash("aaaaaaaaaaaaaaaabbbbbbbbbbbb"),
+        0xb680f9e81f21b2bbu64 as i64
+    );
+    assert_eq!(
+        compute_tag_hash("example.ComplexObject"),
+        0x7802f0457ca09f51
+    );
+
+    let bin = to_buffer(&Animal {
+        name: "dog".to_string(),
+    });
+    // head, ref flag, type and tag flag
+    let offset = 10 + 3 + 1;
+    assert_eq!(
+        bin[offset..offset + 8],
+        compute_tag_hash("example.animal").to_le_bytes()
+    );
+
+    let mut bad = bin.clone();
+    bad[offset] ^= 1;
+    let obj: Result<Animal, Error> = from_buffer(&bad);
+    assert!(matches!(obj, Err(Error::TagHash { .. })));
+}
+
+#[test]
+fn tag_id() {
+    let value = vec![
+        Animal {
+            name: "dog".to_string(),
+        },
+        Animal {
+            name: "cat".to_string(),
+        },
+    ];
+    let bin = to_buffer(&value);
+    assert_eq!(serialized_size(&value), bin.len());
+    // the tag is written once, the second animal refers to it by id
+    let tag = b"example.animal";
+    assert_eq!(bin.windows(tag.len()).filter(|w| w == tag).count(), 1);
+    let obj: Vec<Animal> = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, value);
+}
+
+#[test]
+fn many_tags() {
+    // more tags than the table keeps inline, each is written once and referred to by id after that
+    let value = Value::List(
+        (0..24)
+            .map(|i| Value::Struct {
+                tag: format!("example.tag{}", i % 12),
+                fields: vec![("x".to_string(), Value::Int32(i))],
+            })
+            .collect(),
+    );
+    let bin = to_buffer(&value);
+    assert_eq!(serialized_size(&value), bin.len());
+    let obj: Value = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, value);
+}
@@ -0,0 +1,144 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use fury::{
+    from_buffer, serialized_size, to_buffer, to_buffer_with_config, Config, Error, Fury, Value,
+};
+
+#[derive(Fury, Debug, PartialEq)]
+#[tag("example.point")]
+struct Point(i32, String, Option<i64>);
+
+#[derive(Fury, Debug, PartialEq, Default)]
+#[tag("example.version")]
+#[compatible]
+struct Version(u32, u32);
+
+#[derive(Fury, Debug, PartialEq)]
+#[tag("example.marker")]
+struct Marker;
+
+#[derive(Fury, Debug, PartialEq)]
+struct UserId(u64);
+
+#[derive(Fury, Debug, PartialEq)]
+#[tag("example.session")]
+struct Session {
+    user: UserId,
+    owner: Option<UserId>,
+    point: Point,
+    marker: Marker,
+    pairs: Vec<(String, i32)>,
+}
+
+#[test]
+fn tuple() {
+    let value = (1_i32, "a".to_string(), Some(2_i64), vec![1_u8, 2]);
+    let bin = to_buffer(&value);
+    assert_eq!(serialized_size(&value), bin.len());
+    let obj: (i32, String, Option<i64>, Vec<u8>) = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, value);
+}
+
+#[test]
+fn tuple_is_list() {
+    let bin = to_buffer(&(1_i32, "a".to_string()));
+    let obj: Value = from_buffer(&bin).expect("should success");
+    assert_eq!(
+        obj,
+        Value::List(vec![Value::Int32(1), Value::String("a".to_string())])
+    );
+}
+
+#[test]
+fn tuple_of_twelve() {
+    let value = (
+        1_u8,
+        2_i8,
+        3_u16,
+        4_i16,
+        5_u32,
+        6_i32,
+        7_u64,
+        8_i64,
+        9.0_f32,
+        10.0_f64,
+        true,
+        'x'.to_string(),
+    );
+    let bin = to_buffer(&value);
+    let obj: (u8, i8, u16, i16, u32, i32, u64, i64, f32, f64, bool, String) =
+        from_buffer(&bin).expect("should success");
+    assert_eq!(obj, value);
+}
+
+#[test]
+fn tuple_length() {
+    let bin = to_buffer(&(1_i32, 2_i32, 3_i32));
+    let err = from_buffer::<(i32, i32)>(&bin).expect_err("should fail");
+    assert!(matches!(
+        err,
+        Error::TupleLength {
+            expected: 2,
+            actual: 3
+        }
+    ));
+}
+
+#[test]
+fn newtype() {
+    // a newtype is written exactly like the type it wraps
+    assert_eq!(to_buffer(&UserId(7)), to_buffer(&7_u64));
+    let obj: UserId = from_buffer(&to_buffer(&7_u64)).expect("should success");
+    assert_eq!(obj, UserId(7));
+    let obj: Option<UserId> = from_buffer(&to_buffer(&None::<u64>)).expect("should success");
+    assert_eq!(obj, None);
+
+    // a Vec of them too, as a primitive array
+    let bin = to_buffer(&vec![UserId(1), UserId(2)]);
+    assert_eq!(bin, to_buffer(&vec![1_u64, 2]));
+    assert_eq!(serialized_size(&vec![UserId(1), UserId(2)]), bin.len());
+    let obj: Vec<UserId> = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, vec![UserId(1), UserId(2)]);
+}
+
+#[test]
+fn tuple_structs() {
+    let value = Session {
+        user: UserId(1),
+        owner: None,
+        point: Point(3, "b".to_string(), None),
+        marker: Marker,
+        pairs: vec![("c".to_string(), 4)],
+    };
+    let bin = to_buffer(&value);
+    assert_eq!(serialized_size(&value), bin.len());
+    let obj: Session = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, value);
+
+    let config = Config {
+        native: true,
+        ..Default::default()
+    };
+    let bin = to_buffer_with_config(&value, &config);
+    let obj: Session = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, value);
+}
+
+#[test]
+fn compatible_tuple_struct() {
+    let bin = to_buffer(&Version(1, 2));
+    let obj: Version = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, Version(1, 2));
+}
@@ -0,0 +1,193 @@
+// Copyright 2023 The Fury Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::NaiveDate;
+use fury::__derive::FuryMeta;
+use fury::{from_buffer, serialized_size, to_buffer, Error, Fury, Value};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[test]
+fn mixed_list() {
+    let date = NaiveDate::from_ymd_opt(2023, 7, 1).unwrap();
+    let value = vec![
+        Value::Null,
+        Value::Bool(true),
+        Value::UInt8(1),
+        Value::Int8(-1),
+        Value::UInt16(2),
+        Value::Int16(-2),
+        Value::UInt32(3),
+        Value::Int32(-3),
+        Value::UInt64(4),
+        Value::Int64(-4),
+        Value::Float(1.5),
+        Value::Double(2.5),
+        Value::String("hello".to_string()),
+        Value::Binary(vec![1, 2, 3]),
+        Value::Date(date),
+        Value::Timestamp(date.and_hms_opt(1, 2, 3).unwrap()),
+        Value::BoolArray(vec![true, false]),
+        Value::Int16Array(vec![1, 2]),
+        Value::Int32Array(vec![3, 4]),
+        Value::Int64Array(vec![5, 6]),
+        Value::FloatArray(vec![7.0]),
+        Value::DoubleArray(vec![8.0]),
+        Value::StringArray(vec!["a".to_string(), "b".to_string()]),
+        Value::List(vec![Value::Int32(1), Value::List(vec![])]),
+        Value::Set(vec![Value::String("x".to_string())]),
+        Value::Map(vec![(Value::Int32(1), Value::Double(1.0))]),
+        Value::Struct {
+            tag: "example.point".to_string(),
+            fields: vec![("x".to_string(), Value::Int32(1))],
+        },
+    ];
+    let bin = to_buffer(&value);
+    assert_eq!(serialized_size(&value), bin.len());
+    let obj: Vec<Value> = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, value);
+
+    let obj: Value = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, Value::List(value));
+
+    let obj: Value = from_buffer(&to_buffer(&None::<i32>)).expect("should success");
+    assert_eq!(obj, Value::Null);
+}
+
+#[test]
+fn typed_payload() {
+    // a dict of pyfury or a Map<String, Object> of java
+    let value = HashMap::from([
+        ("ids".to_string(), vec![1i64, 2]),
+        ("empty".to_string(), vec![]),
+    ]);
+    let obj: HashMap<String, Value> = from_buffer(&to_buffer(&value)).expect("should success");
+    assert_eq!(
+        obj,
+        HashMap::from([
+            ("ids".to_string(), Value::Int64Array(vec![1, 2])),
+            ("empty".to_string(), Value::Int64Array(vec![])),
+        ])
+    );
+
+    let obj: HashMap<String, Value> = from_buffer(&to_buffer(&HashMap::from([
+        ("a".to_string(), Value::Int32(1)),
+        ("b".to_string(), Value::String("c".to_string())),
+    ])))
+    .expect("should success");
+    assert_eq!(obj["a"], Value::Int32(1));
+    assert_eq!(obj["b"], Value::String("c".to_string()));
+}
+
+#[test]
+fn structs() {
+    #[derive(Fury, Debug, PartialEq, Default)]
+    #[tag("example.point")]
+    #[compatible]
+    struct Point {
+        x: i32,
+        y: String,
+    }
+
+    #[derive(Fury, Debug, PartialEq)]
+    #[tag("example.consistent_point")]
+    struct ConsistentPoint {
+        x: i32,
+    }
+
+    let value = Point {
+        x: 1,
+        y: "a".to_string(),
+    };
+    let obj: Value = from_buffer(&to_buffer(&value)).expect("should success");
+    let expected = Value::Struct {
+        tag: "example.point".to_string(),
+        fields: vec![
+            ("x".to_string(), Value::Int32(1)),
+            ("y".to_string(), Value::String("a".to_string())),
+        ],
+    };
+    assert_eq!(obj, expected);
+    let obj: Point = from_buffer(&to_buffer(&expected)).expect("should success");
+    assert_eq!(obj, value);
+
+    let obj: Result<Value, Error> = from_buffer(&to_buffer(&ConsistentPoint { x: 1 }));
+    assert!(matches!(obj, Err(Error::UntypedStruct(tag)) if tag == "example.consistent_point"));
+}
+
+#[test]
+fn value_field() {
+    #[derive(Fury, Debug, PartialEq)]
+    #[tag("example.event")]
+    struct Event {
+        name: String,
+        payload: Value,
+    }
+
+    #[derive(Fury, Debug, PartialEq)]
+    #[tag("example.event")]
+    struct EventName {
+        name: String,
+    }
+
+    // java leaves Object fields out of the hash
+    assert_eq!(Event::hash(), EventName::hash());
+
+    let value = Event {
+        name: "click".to_string(),
+        payload: Value::List(vec![Value::Int32(1), Value::Null]),
+    };
+    let obj: Event = from_buffer(&to_buffer(&value)).expect("should success");
+    assert_eq!(obj, value);
+}
+
+#[test]
+fn shared_value() {
+    let value = Rc::new(Value::Null);
+    let obj: Rc<Value> = from_buffer(&to_buffer(&value)).expect("should success");
+    assert_eq!(obj, value);
+
+    // the type id of the value follows the ref flag, only the flag differs from a Value which isn't shared
+    let value = Rc::new(Value::Int32(7));
+    let bin = to_buffer(&value);
+    assert_eq!(serialized_size(&value), bin.len());
+    let plain = to_buffer(&Value::Int32(7));
+    assert_eq!(bin.len(), plain.len());
+    let diff: Vec<_> = bin
+        .iter()
+        .zip(plain.iter())
+        .filter(|(a, b)| a != b)
+        .collect();
+    assert_eq!(diff, vec![(&0, &(-1_i8 as u8))]);
+    let obj: Rc<Value> = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, value);
+}
+
+#[test]
+fn shared_in_list() {
+    // a list java writes with the same object twice
+    let item = Rc::new(Value::String("a".to_string()));
+    let bin = to_buffer(&vec![item.clone(), item]);
+    let obj: Vec<Value> = from_buffer(&bin).expect("should success");
+    match obj.as_slice() {
+        [Value::Shared(first), Value::Shared(second)] => {
+            assert!(Rc::ptr_eq(first, second));
+            assert_eq!(**first, Value::String("a".to_string()));
+        }
+        _ => panic!("should be shared: {:?}", obj),
+    }
+    // written back with the same refs
+    assert_eq!(to_buffer(&obj), bin);
+    assert_eq!(serialized_size(&obj), bin.len());
+}
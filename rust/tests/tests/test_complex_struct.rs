@@ -13,9 +13,10 @@
 // limitations under the License.
 
 use chrono::{NaiveDate, NaiveDateTime};
-use fury::{from_buffer, to_buffer};
+use fury::__derive::FuryMeta;
+use fury::{from_buffer, serialized_size, to_buffer};
 use fury_derive::Fury;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[test]
 fn complex_struct() {
@@ -64,6 +65,7 @@ fn complex_struct() {
     };
 
     let bin: Vec<u8> = to_buffer(&person);
+    assert_eq!(serialized_size(&person), bin.len());
     let obj: Person = from_buffer(&bin).expect("should success");
     assert_eq!(person, obj);
 }
@@ -109,6 +111,55 @@ fn decode_py_struct() {
 
     let obj: Person = from_buffer(&bin).expect("should some");
     print!("{:?}", obj);
+    // python writes List[int16] as a list of headed elements
+    assert_eq!(obj.f9, vec![1, 2]);
+}
+
+#[test]
+fn struct_hash() {
+    #[derive(Fury, Debug, PartialEq)]
+    #[tag("example.bar")]
+    struct Bar {
+        category: String,
+    }
+
+    #[derive(Fury, Debug, PartialEq)]
+    #[tag("example.foo")]
+    struct Foo {
+        r#type: i8,
+        scores: HashMap<String, Vec<f64>>,
+        ids: HashSet<i32>,
+        date: NaiveDate,
+        created: NaiveDateTime,
+        bin: Vec<u8>,
+        bar: Option<Bar>,
+        animals: Vec<Bar>,
+    }
+
+    // the hashes java and pyfury compute for the same classes in `testStructHash` and
+    // `test_struct_hash`, the fields are sorted by their names: animals, bar, bin, created,
+    // date, ids, scores, type. Both leave the set out
+    assert_eq!(<Bar as FuryMeta>::hash(), 540);
+    assert_eq!(<Foo as FuryMeta>::hash(), 1300969400);
+
+    let foo = Foo {
+        r#type: 1,
+        scores: HashMap::from([("a".to_string(), vec![1.0])]),
+        ids: HashSet::from([1]),
+        date: NaiveDate::from_ymd_opt(2025, 12, 12).unwrap(),
+        created: NaiveDateTime::from_timestamp_opt(1689912359, 0).unwrap(),
+        bin: vec![1, 2],
+        bar: Some(Bar {
+            category: "dog".to_string(),
+        }),
+        animals: vec![],
+    };
+    let bin = to_buffer(&foo);
+    // the first field after the tag and the hash is animals
+    let offset = 10 + 3 + 1 + 8 + 2 + "example.foo".len() + 4;
+    assert_eq!(&bin[offset..offset + 4], &[0xff, 25, 0, 0]);
+    let obj: Foo = from_buffer(&bin).expect("should success");
+    assert_eq!(obj, foo);
 }
 
 #[test]
@@ -14,7 +14,7 @@
 
 use std::collections::BTreeMap;
 
-use fury::{from_row, to_row, FuryRow};
+use fury::{from_row, to_row, to_row_slice, Error, FuryRow};
 
 #[test]
 fn row() {
@@ -36,7 +36,7 @@ fn row() {
     f5.insert(String::from("k1"), String::from("v1"));
     f5.insert(String::from("k2"), String::from("v2"));
 
-    let row = to_row(&Bar {
+    let bar = Bar {
         f3: Foo {
             f1: String::from("hello"),
             f2: 1,
@@ -44,7 +44,16 @@ fn row() {
             f4: vec![-1, 2, -3],
             f5,
         },
-    });
+    };
+    let row = to_row(&bar);
+
+    let mut bf = [0u8; 512];
+    let len = to_row_slice(&bar, &mut bf).expect("should fit");
+    assert_eq!(&bf[..len], row.as_slice());
+    assert!(matches!(
+        to_row_slice(&bar, &mut bf[..len / 2]),
+        Err(Error::BufferFull { needed, .. }) if needed == len
+    ));
 
     let obj = from_row::<Bar>(&row);
     let f1: &str = obj.f3().f1();
@@ -71,3 +80,33 @@ fn row() {
     assert_eq!(f5.get("k1").expect("should exists"), &"v1");
     assert_eq!(f5.get("k2").expect("should exists"), &"v2");
 }
+
+#[test]
+fn fixed_arrays() {
+    #[derive(FuryRow)]
+    struct Mesh {
+        hash: [u8; 4],
+        normal: [f32; 3],
+    }
+
+    let mesh = Mesh {
+        hash: [1, 2, 3, 4],
+        normal: [0.0, 1.0, 0.5],
+    };
+    let row = to_row(&mesh);
+    let obj = from_row::<Mesh>(&row);
+    assert_eq!(obj.hash().expect("should be 4 bytes"), &[1, 2, 3, 4]);
+    let normal = obj.normal().expect("should be 3 elements");
+    assert_eq!(normal.size(), 3);
+    assert_eq!(normal.get(2), 0.5);
+
+    let row = to_row(&[1i32, 2, 3]);
+    assert!(matches!(
+        from_row::<[i32; 4]>(&row),
+        Err(Error::ArrayLength {
+            expected: 4,
+            actual: 3
+        })
+    ));
+    assert!(from_row::<[i32; 3]>(&row).is_ok());
+}